@@ -2,8 +2,9 @@ use std::collections::BTreeSet;
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use positioning::{
+    jump_point::jump_point_search,
     pathfinding::{all_pairs_shortest_paths, HammingDistance, Heuristic},
-    Position,
+    Position, Position2,
 };
 
 fn benchmark(c: &mut Criterion) {
@@ -17,6 +18,14 @@ fn benchmark(c: &mut Criterion) {
                 statically_open.insert(Position::new(x, y, 0));
             }
         }
+        c.bench_with_input(
+            BenchmarkId::new("all_pairs_construction", format!("n = {}", n)),
+            &statically_open,
+            |b, s| {
+                b.iter(|| all_pairs_shortest_paths(s));
+            },
+        );
+
         let all_pairs = all_pairs_shortest_paths(&statically_open);
 
         let mut dynamically_open = statically_open.clone();
@@ -30,7 +39,7 @@ fn benchmark(c: &mut Criterion) {
             |b, d| {
                 b.iter(|| {
                     let _ = all_pairs.find_shortest_path(
-                        &d,
+                        d,
                         Position::new(n - 1, n - 1, 0),
                         Position::new(0, n - 1, 0),
                     );
@@ -43,13 +52,94 @@ fn benchmark(c: &mut Criterion) {
             |b, d| {
                 b.iter(|| {
                     let _ = hamming_distance.find_shortest_path(
-                        &d,
+                        d,
                         Position::new(n - 1, n - 1, 0),
                         Position::new(0, n - 1, 0),
                     );
                 });
             },
         );
+        c.bench_with_input(
+            BenchmarkId::new("jump_point", format!("n = {}", n)),
+            &dynamically_open,
+            |b, d| {
+                b.iter(|| {
+                    let _ = jump_point_search(
+                        d,
+                        Position::new(n - 1, n - 1, 0),
+                        Position::new(0, n - 1, 0),
+                        u64::MAX,
+                    );
+                });
+            },
+        );
+
+        // Across open fields jump point search expands only a handful of positions, but still
+        // scans most of the field, while A* guided by an exact heuristic heads straight across it.
+        let open_field: BTreeSet<Position2> = (0..n)
+            .flat_map(|x| (0..n).map(move |y| Position2::new(x, y)))
+            .collect();
+        c.bench_with_input(
+            BenchmarkId::new("open_field_hamming", format!("n = {}", n)),
+            &open_field,
+            |b, o| {
+                b.iter(|| {
+                    let _ = hamming_distance.find_shortest_path(
+                        o,
+                        Position2::new(0, 0),
+                        Position2::new(n - 1, n - 1),
+                    );
+                });
+            },
+        );
+        c.bench_with_input(
+            BenchmarkId::new("open_field_jump_point", format!("n = {}", n)),
+            &open_field,
+            |b, o| {
+                b.iter(|| {
+                    let _ = jump_point_search(
+                        o,
+                        Position2::new(0, 0),
+                        Position2::new(n - 1, n - 1),
+                        u64::MAX,
+                    );
+                });
+            },
+        );
+
+        let open_volume: BTreeSet<Position> = (0..n / 4)
+            .flat_map(|x| {
+                (0..n / 4).flat_map(move |y| (0..n / 4).map(move |z| Position::new(x, y, z)))
+            })
+            .collect();
+        let corner = n / 4 - 1;
+        c.bench_with_input(
+            BenchmarkId::new("open_volume_hamming", format!("n = {}", n / 4)),
+            &open_volume,
+            |b, o| {
+                b.iter(|| {
+                    let _ = hamming_distance.find_shortest_path(
+                        o,
+                        Position::new(0, 0, 0),
+                        Position::new(corner, corner, corner),
+                    );
+                });
+            },
+        );
+        c.bench_with_input(
+            BenchmarkId::new("open_volume_jump_point", format!("n = {}", n / 4)),
+            &open_volume,
+            |b, o| {
+                b.iter(|| {
+                    let _ = jump_point_search(
+                        o,
+                        Position::new(0, 0, 0),
+                        Position::new(corner, corner, corner),
+                        u64::MAX,
+                    );
+                });
+            },
+        );
     }
 }
 
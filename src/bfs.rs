@@ -1,41 +1,103 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
-use crate::Position;
+use crate::{
+    neighborhood::{Neighborhood, VonNeumann},
+    Coordinate, Node, Passable, Position,
+};
 
 /// # Breadth First Search
 ///
 /// An iterator which performs a breadth first search of a set of open tiles, returning
 /// [`Position`]s and their distances away from the given start in order of those distances.
 /// If there are multiple positions at the same distance away, the first one in the order defined on
-/// [`Position`] will be returned first.
-pub struct Bfs<'a> {
-    open_positions: &'a BTreeSet<Position>,
-    visited: BTreeSet<Position>,
+/// [`Position`] will be returned first. Any other [`Coordinate`] type, such as a
+/// [`Position2`](crate::Position2), can be searched in the same way, as can any other [`Node`]
+/// type when given a [`Neighborhood`] describing how to move between them.
+///
+/// Paths to the positions returned can be found with [`Bfs::path_to`] after calling
+/// [`Bfs::record_predecessors`].
+pub struct Bfs<'a, P: ?Sized = BTreeSet<Position>, N: ?Sized = VonNeumann, C = Position> {
+    open_positions: &'a P,
+    neighborhood: &'a N,
+    visited: BTreeSet<C>,
     distance: u64,
-    current_frontier: BTreeSet<Position>,
-    next_frontier: BTreeSet<Position>,
+    current_frontier: BTreeSet<C>,
+    next_frontier: BTreeSet<C>,
+    predecessors: Option<BTreeMap<C, C>>,
 }
 
-impl<'a> Bfs<'a> {
-    pub fn new(open_positions: &'a BTreeSet<Position>, start: Position) -> Self {
-        let mut current_frontier = BTreeSet::new();
+impl<'a, C: Coordinate, P: Passable<C> + ?Sized> Bfs<'a, P, VonNeumann, C> {
+    pub fn new(open_positions: &'a P, start: C) -> Self {
+        Bfs::with_neighborhood(open_positions, start, &VonNeumann)
+    }
 
-        if open_positions.contains(&start) {
-            current_frontier.insert(start);
-        }
+    /// Creates a [`Bfs`] which searches out from all of the given starts at once, returning each
+    /// position along with its distance from the nearest of them.
+    pub fn from_starts<I: IntoIterator<Item = C>>(open_positions: &'a P, starts: I) -> Self {
+        Bfs::from_starts_with_neighborhood(open_positions, starts, &VonNeumann)
+    }
+}
+
+impl<'a, C: Node, P: Passable<C> + ?Sized, N: Neighborhood<C> + ?Sized> Bfs<'a, P, N, C> {
+    /// Creates a [`Bfs`] which moves between positions according to the given [`Neighborhood`].
+    pub fn with_neighborhood(open_positions: &'a P, start: C, neighborhood: &'a N) -> Self {
+        Bfs::from_starts_with_neighborhood(open_positions, [start], neighborhood)
+    }
+
+    /// Creates a [`Bfs`] which searches out from all of the given starts at once, moving between
+    /// positions according to the given [`Neighborhood`].
+    pub fn from_starts_with_neighborhood<I: IntoIterator<Item = C>>(
+        open_positions: &'a P,
+        starts: I,
+        neighborhood: &'a N,
+    ) -> Self {
+        let current_frontier = starts
+            .into_iter()
+            .filter(|start| open_positions.is_passable(*start))
+            .collect();
 
         Bfs {
             open_positions,
+            neighborhood,
             visited: BTreeSet::new(),
             distance: 0,
             current_frontier,
             next_frontier: BTreeSet::new(),
+            predecessors: None,
+        }
+    }
+
+    /// Records the position each position was reached from as the search goes, so that
+    /// [`Bfs::path_to`] can find paths.
+    pub fn record_predecessors(mut self) -> Self {
+        self.predecessors.get_or_insert_with(BTreeMap::new);
+        self
+    }
+
+    /// Finds a shortest path to a position which has already been returned by the search, from
+    /// whichever start it was reached from. As with
+    /// [`Heuristic::find_shortest_path`](crate::pathfinding::Heuristic::find_shortest_path), the
+    /// path excludes the start and includes `position`. Returns [`None`] if the position has not
+    /// been returned yet, or if [`Bfs::record_predecessors`] was not called.
+    pub fn path_to(&self, position: C) -> Option<VecDeque<C>> {
+        let predecessors = self.predecessors.as_ref()?;
+        if !self.visited.contains(&position) {
+            return None;
         }
+        let mut path = VecDeque::new();
+        let mut current_position = position;
+        while let Some(previous) = predecessors.get(&current_position) {
+            path.push_front(current_position);
+            current_position = *previous;
+        }
+        Some(path)
     }
 }
 
-impl<'a> Iterator for Bfs<'a> {
-    type Item = (Position, u64);
+impl<'a, C: Node, P: Passable<C> + ?Sized, N: Neighborhood<C> + ?Sized> Iterator
+    for Bfs<'a, P, N, C>
+{
+    type Item = (C, u64);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.current_frontier.pop_first() {
@@ -49,19 +111,106 @@ impl<'a> Iterator for Bfs<'a> {
             }
             Some(cursor) => {
                 self.visited.insert(cursor);
-                for neighbor in cursor
-                    .adjacent()
-                    .filter(|neighbor| self.open_positions.contains(&neighbor))
+                for neighbor in self
+                    .neighborhood
+                    .neighbors(cursor)
+                    .filter(|neighbor| self.open_positions.is_passable(*neighbor))
                 {
-                    if self.visited.contains(&neighbor) {
+                    if self.visited.contains(&neighbor) || self.current_frontier.contains(&neighbor)
+                    {
                         continue;
                     }
-                    self.next_frontier.insert(neighbor);
+                    if self.next_frontier.insert(neighbor) {
+                        if let Some(predecessors) = &mut self.predecessors {
+                            predecessors.insert(neighbor, cursor);
+                        }
+                    }
+                }
+                Some((cursor, self.distance))
+            }
+        }
+    }
+}
+
+/// Finds a shortest path from `start` to `end` by running a [`Bfs`] out from each of them, one
+/// layer at a time, until they meet. As with
+/// [`Heuristic::find_shortest_path`](crate::pathfinding::Heuristic::find_shortest_path), the path
+/// excludes `start` and includes `end`, but both of them must be open.
+pub fn bidirectional_shortest_path<C: Coordinate, P: Passable<C> + ?Sized>(
+    open_positions: &P,
+    start: C,
+    end: C,
+) -> Option<VecDeque<C>> {
+    bidirectional_shortest_path_with(open_positions, start, end, &VonNeumann)
+}
+
+/// Finds a shortest path from `start` to `end` as in [`bidirectional_shortest_path`], moving
+/// between positions according to the given [`Neighborhood`], which must be
+/// [symmetric](Neighborhood#symmetry).
+pub fn bidirectional_shortest_path_with<
+    C: Node,
+    P: Passable<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    open_positions: &P,
+    start: C,
+    end: C,
+    neighborhood: &N,
+) -> Option<VecDeque<C>> {
+    if !open_positions.is_passable(start) || !open_positions.is_passable(end) {
+        return None;
+    }
+
+    let mut searches = [
+        Bfs::with_neighborhood(open_positions, start, neighborhood).peekable(),
+        Bfs::with_neighborhood(open_positions, end, neighborhood).peekable(),
+    ];
+    let mut reached: [BTreeMap<C, u64>; 2] = Default::default();
+    let meeting = loop {
+        // Grow whichever search has reached fewer positions by a whole layer. Every path shorter
+        // than one through the best meeting position in that layer would have met already.
+        let side = if reached[0].len() <= reached[1].len() {
+            0
+        } else {
+            1
+        };
+        let (_, layer) = *searches[side].peek()?;
+        let mut meeting: Option<(u64, C)> = None;
+        while let Some((position, distance)) =
+            searches[side].next_if(|(_, distance)| *distance == layer)
+        {
+            reached[side].insert(position, distance);
+            if let Some(other) = reached[1 - side].get(&position) {
+                if meeting.is_none_or(|(length, _)| distance + other < length) {
+                    meeting = Some((distance + other, position));
                 }
-                return Some((cursor, self.distance));
             }
         }
+        if let Some((_, position)) = meeting {
+            break position;
+        }
+    };
+
+    // Every layer closer to either end than the meeting position has been fully searched, so
+    // walking downhill through the distances reached from each end retraces a shortest path.
+    let downhill = |reached: &BTreeMap<C, u64>, position: C| {
+        let distance = reached[&position];
+        neighborhood
+            .neighbors(position)
+            .find(|neighbor| reached.get(neighbor).is_some_and(|d| *d + 1 == distance))
+    };
+    let mut path = VecDeque::new();
+    let mut current_position = meeting;
+    while current_position != start {
+        path.push_front(current_position);
+        current_position = downhill(&reached[0], current_position)?;
+    }
+    current_position = meeting;
+    while current_position != end {
+        current_position = downhill(&reached[1], current_position)?;
+        path.push_back(current_position);
     }
+    Some(path)
 }
 
 #[test]
@@ -82,3 +231,151 @@ fn test_bfs() {
         assert_eq!(distance, origin.hamming_distance(position));
     }
 }
+
+#[test]
+fn test_bfs_passable() {
+    use std::collections::HashSet;
+
+    let open_positions: HashSet<Position> = (0..10).map(|i| Position::new(i, 0, 0)).collect();
+    let below_ground = |position: Position| position.z < 0 && position.x == 0 && position.y == 0;
+
+    let hashed: Vec<(Position, u64)> = Bfs::new(&open_positions, Position::new(0, 0, 0)).collect();
+    assert_eq!(hashed.len(), 10);
+    assert_eq!(hashed.last(), Some(&(Position::new(9, 0, 0), 9)));
+
+    let mut closure = Bfs::new(&below_ground, Position::new(0, 0, -1));
+    assert_eq!(closure.nth(5), Some((Position::new(0, 0, -6), 5)));
+}
+
+#[test]
+fn test_bfs_neighborhood() {
+    use crate::neighborhood::{Moore, PlanarMoore};
+
+    let mut open_positions = BTreeSet::new();
+    for i in 0..10 {
+        for j in 0..10 {
+            for k in 0..2 {
+                open_positions.insert(Position::new(i, j, k));
+            }
+        }
+    }
+
+    let origin = Position::new(0, 0, 0);
+
+    for (position, distance) in Bfs::with_neighborhood(&open_positions, origin, &Moore) {
+        assert_eq!(distance, origin.chebyshev_distance(position));
+    }
+
+    let planar: Vec<(Position, u64)> =
+        Bfs::with_neighborhood(&open_positions, origin, &PlanarMoore).collect();
+    assert_eq!(planar.len(), 100);
+    for (position, distance) in planar {
+        assert_eq!(position.z, 0);
+        assert_eq!(distance, origin.chebyshev_distance(position));
+    }
+}
+
+#[test]
+fn test_bfs_position2() {
+    use crate::{neighborhood::Moore, Position2};
+
+    let open_positions: BTreeSet<Position2> = (0..10)
+        .flat_map(|i| (0..10).map(move |j| Position2::new(i, j)))
+        .collect();
+
+    let origin = Position2::new(0, 0);
+
+    for (position, distance) in Bfs::new(&open_positions, origin) {
+        assert_eq!(distance, origin.hamming_distance(position));
+    }
+
+    for (position, distance) in Bfs::with_neighborhood(&open_positions, origin, &Moore) {
+        assert_eq!(distance, origin.chebyshev_distance(position));
+    }
+}
+
+#[test]
+fn test_bfs_from_starts() {
+    use crate::Position2;
+
+    let open_positions: BTreeSet<Position2> = (0..10)
+        .flat_map(|i| (0..10).map(move |j| Position2::new(i, j)))
+        .collect();
+    let starts = [
+        Position2::new(0, 0),
+        Position2::new(9, 4),
+        Position2::new(20, 0),
+    ];
+
+    let mut previous = 0;
+    let mut count = 0;
+    for (position, distance) in Bfs::from_starts(&open_positions, starts) {
+        let nearest = starts[..2]
+            .iter()
+            .map(|start| start.hamming_distance(position))
+            .min();
+        assert_eq!(Some(distance), nearest);
+        assert!(distance >= previous);
+        previous = distance;
+        count += 1;
+    }
+    assert_eq!(count, 100);
+}
+
+#[test]
+fn test_bfs_path_to() {
+    use crate::{test_util::assert_valid_path, Position2};
+
+    // A room with a pillar in the middle, searched until reaching the far side of the pillar.
+    let open_positions: BTreeSet<Position2> = (0..7)
+        .flat_map(|i| (0..7).map(move |j| Position2::new(i, j)))
+        .filter(|p| !((2..5).contains(&p.x) && (2..5).contains(&p.y)))
+        .collect();
+    let start = Position2::new(3, 1);
+    let target = Position2::new(3, 5);
+
+    let mut bfs = Bfs::new(&open_positions, start).record_predecessors();
+    assert_eq!(bfs.path_to(target), None);
+    let (_, distance) = bfs.find(|(position, _)| *position == target).unwrap();
+    for (position, distance) in Bfs::new(&open_positions, start).take_while(|(_, d)| *d <= 4) {
+        assert_eq!(
+            bfs.path_to(position).map(|path| path.len() as u64),
+            Some(distance)
+        );
+    }
+    let path = bfs.path_to(target).unwrap();
+    assert_eq!(path.len() as u64, distance);
+    assert_valid_path(&VonNeumann, &open_positions, start, path, target);
+    assert_eq!(bfs.path_to(start), Some(VecDeque::new()));
+
+    let mut unrecorded = Bfs::new(&open_positions, start);
+    assert!(unrecorded.any(|(position, _)| position == target));
+    assert_eq!(unrecorded.path_to(target), None);
+}
+
+#[test]
+fn test_bidirectional_shortest_path() {
+    use crate::{neighborhood::Moore, test_util::assert_valid_path, Position2};
+
+    // Two rooms joined by a long corridor, with a pillar in the way.
+    let open_positions: BTreeSet<Position2> = (0..30)
+        .flat_map(|x| (0..7).map(move |y| Position2::new(x, y)))
+        .filter(|p| (p.x < 5 || p.x > 24 || p.y == 3) && !(p.x == 2 && (1..6).contains(&p.y)))
+        .collect();
+
+    let start = Position2::new(0, 3);
+    for neighborhood in [&VonNeumann as &dyn Neighborhood<Position2>, &Moore] {
+        let distances: BTreeMap<Position2, u64> =
+            Bfs::with_neighborhood(&open_positions, start, neighborhood).collect();
+        for end in open_positions.iter().copied() {
+            let path = bidirectional_shortest_path_with(&open_positions, start, end, neighborhood)
+                .unwrap();
+            assert_eq!(path.len() as u64, distances[&end]);
+            assert_valid_path(neighborhood, &open_positions, start, path, end);
+        }
+    }
+    assert_eq!(
+        bidirectional_shortest_path(&open_positions, start, Position2::new(10, 0)),
+        None
+    );
+}
@@ -0,0 +1,285 @@
+//! # Chunks
+//!
+//! A sparse map of open positions for effectively unbounded worlds. Space is divided into cubic
+//! chunks of [`CHUNK_SIZE`] positions along each axis, and only chunks which have been loaded are
+//! stored, each as a bitset of which of its positions are open.
+
+use std::collections::HashMap;
+
+use crate::{Passable, PassableSet, Position};
+
+/// The number of positions along each axis of a [`Chunk`].
+pub const CHUNK_SIZE: i64 = 16;
+
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+const CHUNK_WORDS: usize = CHUNK_VOLUME / 64;
+
+/// A bitset recording which positions within a single chunk are open. Positions within a chunk
+/// are given as local offsets, with each coordinate in `0..CHUNK_SIZE`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chunk {
+    bits: [u64; CHUNK_WORDS],
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Chunk::empty()
+    }
+}
+
+impl Chunk {
+    /// Creates a [`Chunk`] in which every position is blocked.
+    pub fn empty() -> Self {
+        Chunk {
+            bits: [0; CHUNK_WORDS],
+        }
+    }
+
+    /// Creates a [`Chunk`] in which every position is open.
+    pub fn full() -> Self {
+        Chunk {
+            bits: [u64::MAX; CHUNK_WORDS],
+        }
+    }
+
+    /// Creates a [`Chunk`] whose openness at each local offset is given by `f`.
+    pub fn from_fn<F: FnMut(Position) -> bool>(mut f: F) -> Self {
+        let mut chunk = Chunk::empty();
+        for index in 0..CHUNK_VOLUME {
+            if f(Self::local_of(index)) {
+                chunk.bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+        chunk
+    }
+
+    fn index_of(local: Position) -> usize {
+        assert!(
+            (0..CHUNK_SIZE).contains(&local.x)
+                && (0..CHUNK_SIZE).contains(&local.y)
+                && (0..CHUNK_SIZE).contains(&local.z),
+            "local offset out of the bounds of a chunk"
+        );
+        ((local.z * CHUNK_SIZE + local.y) * CHUNK_SIZE + local.x) as usize
+    }
+
+    fn local_of(index: usize) -> Position {
+        let index = index as i64;
+        Position::new(
+            index % CHUNK_SIZE,
+            (index / CHUNK_SIZE) % CHUNK_SIZE,
+            index / (CHUNK_SIZE * CHUNK_SIZE),
+        )
+    }
+
+    /// Returns whether or not the position at the given local offset is open.
+    ///
+    /// Panics if the offset lies outside of the chunk.
+    pub fn is_open(&self, local: Position) -> bool {
+        let index = Self::index_of(local);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Sets whether or not the position at the given local offset is open.
+    ///
+    /// Panics if the offset lies outside of the chunk.
+    pub fn set_open(&mut self, local: Position, open: bool) {
+        let index = Self::index_of(local);
+        if open {
+            self.bits[index / 64] |= 1 << (index % 64);
+        } else {
+            self.bits[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    /// The number of open positions in this chunk.
+    pub fn count_open(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns whether or not every position in this chunk is blocked.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|word| *word == 0)
+    }
+
+    /// Returns an iterator over the local offsets of all open positions in this chunk.
+    pub fn open_offsets(&self) -> impl Iterator<Item = Position> + '_ {
+        self.bits
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| word_index * 64 + bit)
+            })
+            .map(Self::local_of)
+    }
+}
+
+/// A sparse map of open positions made up of loaded [`Chunk`]s keyed by their chunk coordinate.
+/// Positions in chunks which are not loaded are considered blocked, so searches over a
+/// [`ChunkMap`] stay within the currently streamed in terrain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkMap {
+    chunks: HashMap<Position, Chunk>,
+}
+
+impl ChunkMap {
+    /// Creates a new [`ChunkMap`] with no chunks loaded.
+    pub fn new() -> Self {
+        ChunkMap::default()
+    }
+
+    /// Splits a position into the coordinate of the chunk it belongs to and its local offset
+    /// within that chunk:
+    /// ```
+    /// use positioning::{ChunkMap, Position};
+    ///
+    /// assert_eq!(
+    ///     ChunkMap::split(Position::new(17, -1, 0)),
+    ///     (Position::new(1, -1, 0), Position::new(1, 15, 0))
+    /// );
+    /// ```
+    pub fn split(position: Position) -> (Position, Position) {
+        (
+            Position::new(
+                position.x.div_euclid(CHUNK_SIZE),
+                position.y.div_euclid(CHUNK_SIZE),
+                position.z.div_euclid(CHUNK_SIZE),
+            ),
+            Position::new(
+                position.x.rem_euclid(CHUNK_SIZE),
+                position.y.rem_euclid(CHUNK_SIZE),
+                position.z.rem_euclid(CHUNK_SIZE),
+            ),
+        )
+    }
+
+    /// The inverse of [`ChunkMap::split`], combining a chunk coordinate and a local offset back
+    /// into a position.
+    pub fn join(chunk: Position, local: Position) -> Position {
+        chunk * CHUNK_SIZE + local
+    }
+
+    /// Loads a chunk at the given chunk coordinate, returning the chunk which was previously
+    /// loaded there, if any.
+    pub fn load_chunk(&mut self, chunk_coordinate: Position, chunk: Chunk) -> Option<Chunk> {
+        self.chunks.insert(chunk_coordinate, chunk)
+    }
+
+    /// Unloads the chunk at the given chunk coordinate, returning it if it was loaded.
+    pub fn unload_chunk(&mut self, chunk_coordinate: Position) -> Option<Chunk> {
+        self.chunks.remove(&chunk_coordinate)
+    }
+
+    /// Returns whether or not a chunk is loaded at the given chunk coordinate.
+    pub fn is_loaded(&self, chunk_coordinate: Position) -> bool {
+        self.chunks.contains_key(&chunk_coordinate)
+    }
+
+    /// Returns the chunk loaded at the given chunk coordinate.
+    pub fn chunk(&self, chunk_coordinate: Position) -> Option<&Chunk> {
+        self.chunks.get(&chunk_coordinate)
+    }
+
+    /// Returns a mutable reference to the chunk loaded at the given chunk coordinate.
+    pub fn chunk_mut(&mut self, chunk_coordinate: Position) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&chunk_coordinate)
+    }
+
+    /// Returns an iterator over the coordinates of all loaded chunks, in no particular order.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = Position> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Returns whether or not the given position is open. Positions in unloaded chunks are never
+    /// open.
+    pub fn is_open(&self, position: Position) -> bool {
+        let (chunk_coordinate, local) = Self::split(position);
+        self.chunks
+            .get(&chunk_coordinate)
+            .is_some_and(|chunk| chunk.is_open(local))
+    }
+
+    /// Sets whether or not the given position is open. If its chunk is not loaded, an empty chunk
+    /// is loaded first.
+    pub fn set_open(&mut self, position: Position, open: bool) {
+        let (chunk_coordinate, local) = Self::split(position);
+        self.chunks
+            .entry(chunk_coordinate)
+            .or_default()
+            .set_open(local, open);
+    }
+}
+
+impl Passable for ChunkMap {
+    fn is_passable(&self, position: Position) -> bool {
+        self.is_open(position)
+    }
+}
+
+impl PassableSet for ChunkMap {
+    fn passable_positions(&self) -> Box<dyn Iterator<Item = Position> + '_> {
+        Box::new(self.chunks.iter().flat_map(|(chunk_coordinate, chunk)| {
+            chunk
+                .open_offsets()
+                .map(move |local| ChunkMap::join(*chunk_coordinate, local))
+        }))
+    }
+}
+
+#[test]
+fn chunk_map_test() {
+    use crate::{
+        bfs::Bfs,
+        pathfinding::{HammingDistance, Heuristic},
+    };
+
+    for position in [
+        Position::new(0, 0, 0),
+        Position::new(-1, -16, 15),
+        Position::new(i64::MIN, i64::MAX, 33),
+    ] {
+        let (chunk_coordinate, local) = ChunkMap::split(position);
+        assert_eq!(ChunkMap::join(chunk_coordinate, local), position);
+    }
+
+    let floor = Chunk::from_fn(|local| local.z == 0);
+    assert_eq!(floor.count_open(), (CHUNK_SIZE * CHUNK_SIZE) as usize);
+    assert!(floor.open_offsets().all(|local| floor.is_open(local)));
+
+    let mut chunk_map = ChunkMap::new();
+    chunk_map.load_chunk(Position::new(0, 0, 0), floor.clone());
+    chunk_map.load_chunk(Position::new(-1, 0, 0), floor);
+    assert!(chunk_map.is_passable(Position::new(-CHUNK_SIZE, 0, 0)));
+    assert!(!chunk_map.is_passable(Position::new(CHUNK_SIZE, 0, 0)));
+    assert_eq!(
+        chunk_map.passable_positions().count(),
+        2 * (CHUNK_SIZE * CHUNK_SIZE) as usize
+    );
+
+    let start = Position::new(-CHUNK_SIZE, 0, 0);
+    let end = Position::new(CHUNK_SIZE - 1, 0, 0);
+    assert_eq!(
+        Bfs::new(&chunk_map, start).find(|(position, _)| *position == end),
+        Some((end, 2 * CHUNK_SIZE as u64 - 1))
+    );
+
+    chunk_map.set_open(Position::new(0, 0, 0), false);
+    chunk_map.set_open(Position::new(0, 1, 0), false);
+    assert_eq!(
+        HammingDistance
+            .find_shortest_path(&chunk_map, start, end)
+            .map(|path| path.len() as u64),
+        Some(2 * CHUNK_SIZE as u64 + 3)
+    );
+
+    chunk_map.unload_chunk(Position::new(0, 0, 0));
+    assert_eq!(
+        HammingDistance.find_shortest_path(&chunk_map, start, end),
+        None
+    );
+}
@@ -0,0 +1,52 @@
+//! # Coordinate
+//!
+//! The common interfaces shared by the position types in this crate, which the searches in
+//! [`bfs`](crate::bfs) and [`pathfinding`](crate::pathfinding) are written against.
+//!
+//! The searches only truly require their nodes to implement [`Node`], as long as they are told
+//! how to move between them by a [`Neighborhood`](crate::neighborhood::Neighborhood), which allows
+//! them to run over arbitrary graphs. Grid coordinates additionally implement [`Coordinate`],
+//! which gives them a default notion of adjacency and distance.
+
+use std::{fmt::Debug, hash::Hash};
+
+/// Anything which can be searched over. Nodes must be totally ordered, as searches use the order
+/// to break ties deterministically. This is implemented for every type meeting these bounds, such
+/// as the identifiers of rooms in a navigation graph.
+pub trait Node: Copy + Ord + Hash + Debug + 'static {}
+
+impl<T: Copy + Ord + Hash + Debug + 'static> Node for T {}
+
+/// A position on some grid, such as a [`Position`](crate::Position) in 3D, a
+/// [`Position2`](crate::Position2) in 2D or a [`Hex`](crate::hex::Hex).
+pub trait Coordinate: Node {
+    /// Returns an iterator over all coordinates adjacent to this one.
+    fn adjacent(self) -> Box<dyn Iterator<Item = Self>>;
+
+    /// Returns the number of steps between adjacent coordinates it takes to get from this
+    /// coordinate to the other one, if nothing is in the way.
+    fn distance(self, other: Self) -> u64;
+
+    /// Returns whether or not we are adjacent to another coordinate.
+    fn is_adjacent_to(self, other: Self) -> bool {
+        self.distance(other) == 1
+    }
+}
+
+#[test]
+fn coordinate_test() {
+    use crate::{hex::Hex, Position, Position2};
+
+    fn check<C: Coordinate>(origin: C) {
+        for neighbor in origin.adjacent() {
+            assert!(origin.is_adjacent_to(neighbor));
+            assert_eq!(origin.distance(neighbor), 1);
+            assert_eq!(neighbor.distance(origin), 1);
+        }
+        assert_eq!(origin.distance(origin), 0);
+    }
+
+    check(Position::new(1, -2, 3));
+    check(Position2::new(1, -2));
+    check(Hex::new(1, -2));
+}
@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use priority_queue::DoublePriorityQueue;
+
+use crate::{
+    bfs::Bfs,
+    neighborhood::{Neighborhood, VonNeumann},
+    Coordinate, MovementCost, Node, Passable, Position, UnitCost,
+};
+
+/// # Dijkstra Map
+///
+/// A field of values over the open positions of a map, which starts out as the distance from each
+/// position to the nearest of a set of sources, such as every enemy or every exit, as popularised
+/// by roguelikes. A unit which always steps to the neighbor with the lowest value, as
+/// [`DijkstraMap::next_step`] does, heads straight for the nearest source along a shortest path,
+/// however many sources there are, and any number of units can share the same map.
+///
+/// Maps can be scaled and inverted to produce other behaviors. Inverting a map of distances to
+/// enemies turns it into one which leads away from them, though only to the nearest corner, so
+/// [`DijkstraMap::fleeing`] also rescans the map, letting units see that running past an enemy
+/// towards open space is better than being cornered.
+///
+/// Only positions which can reach a source are in the map. The [`Neighborhood`] used must be
+/// [symmetric](Neighborhood#symmetry).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DijkstraMap<C: Node = Position, N = VonNeumann> {
+    neighborhood: N,
+    values: BTreeMap<C, i64>,
+}
+
+impl<C: Coordinate> DijkstraMap<C, VonNeumann> {
+    /// Creates a map of the distance from each open position to the nearest of the `sources`.
+    pub fn new<P: Passable<C> + ?Sized>(open_positions: &P, sources: &[C]) -> Self {
+        DijkstraMap::with_neighborhood(open_positions, sources, VonNeumann)
+    }
+
+    /// Creates a map of the cost of the cheapest path from each position to the nearest of the
+    /// `sources`, where the cost of each step is given by the [`MovementCost`] map.
+    pub fn cheapest<M: MovementCost<C> + ?Sized>(costs: &M, sources: &[C]) -> Self {
+        DijkstraMap::cheapest_with(costs, sources, VonNeumann)
+    }
+}
+
+impl<C: Node, N: Neighborhood<C>> DijkstraMap<C, N> {
+    /// Creates a map of distances as in [`DijkstraMap::new`], moving between positions according
+    /// to the given [`Neighborhood`].
+    pub fn with_neighborhood<P: Passable<C> + ?Sized>(
+        open_positions: &P,
+        sources: &[C],
+        neighborhood: N,
+    ) -> Self {
+        let values = Bfs::from_starts_with_neighborhood(
+            open_positions,
+            sources.iter().copied(),
+            &neighborhood,
+        )
+        .map(|(position, distance)| (position, distance as i64))
+        .collect();
+        DijkstraMap {
+            neighborhood,
+            values,
+        }
+    }
+
+    /// Creates a map of costs as in [`DijkstraMap::cheapest`], moving between positions according
+    /// to the given [`Neighborhood`]. A [`MovementCost`] map only knows the cost of steps between
+    /// positions, not whether a position is itself blocked, so every source is kept with a value of
+    /// 0, where [`DijkstraMap::with_neighborhood`] leaves out those which are blocked.
+    pub fn cheapest_with<M: MovementCost<C> + ?Sized>(
+        costs: &M,
+        sources: &[C],
+        neighborhood: N,
+    ) -> Self {
+        let mut map = DijkstraMap {
+            neighborhood,
+            values: sources.iter().map(|source| (*source, 0)).collect(),
+        };
+        map.rescan(costs);
+        map
+    }
+
+    /// The value of the given position, if it is in the map.
+    pub fn get(&self, position: C) -> Option<i64> {
+        self.values.get(&position).copied()
+    }
+
+    /// All of the positions in the map along with their values, in the order defined on `C`.
+    pub fn iter(&self) -> impl Iterator<Item = (C, i64)> + '_ {
+        self.values
+            .iter()
+            .map(|(position, value)| (*position, *value))
+    }
+
+    /// The number of positions in the map.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether there are no positions in the map, which for a map of distances is the case when
+    /// none of the sources were open.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The neighbor of `position` with the lowest value, if that is lower than the value of
+    /// `position` itself.
+    pub fn next_step(&self, position: C) -> Option<C> {
+        let value = self.get(position)?;
+        self.neighborhood
+            .neighbors(position)
+            .filter_map(|neighbor| Some((self.get(neighbor)?, neighbor)))
+            .filter(|(neighbor_value, _)| *neighbor_value < value)
+            .min_by_key(|(neighbor_value, _)| *neighbor_value)
+            .map(|(_, neighbor)| neighbor)
+    }
+
+    /// Follows [`DijkstraMap::next_step`] from `position` until reaching a position with no lower
+    /// neighbor. On a map of distances that is the nearest source. The path excludes `position`.
+    pub fn roll_downhill(&self, position: C) -> VecDeque<C> {
+        let mut path = VecDeque::new();
+        let mut current_position = position;
+        while let Some(next) = self.next_step(current_position) {
+            path.push_back(next);
+            current_position = next;
+        }
+        path
+    }
+
+    /// Multiplies every value in the map by `factor`, rounding to the nearest integer. A negative
+    /// factor turns the map upside down, so that rolling downhill leads away from the sources.
+    pub fn scale(&mut self, factor: f64) {
+        for value in self.values.values_mut() {
+            *value = (*value as f64 * factor).round() as i64;
+        }
+    }
+
+    /// Turns the map upside down, so that rolling downhill leads away from the sources.
+    pub fn invert(&mut self) {
+        for value in self.values.values_mut() {
+            *value = -*value;
+        }
+    }
+
+    /// Lowers the value of every position to at most the cost of stepping to a neighbor plus the
+    /// value of that neighbor, where the cost of each step is given by the [`MovementCost`] map.
+    /// After changing the values of a map, this spreads low values out from wherever they are so
+    /// that rolling downhill follows the cheapest way to reach them. Positions which cannot be
+    /// stepped onto from the neighbor they would be reached from, such as walls, are left out.
+    pub fn rescan<M: MovementCost<C> + ?Sized>(&mut self, costs: &M) {
+        let mut queue: DoublePriorityQueue<C, i64> = self
+            .values
+            .iter()
+            .map(|(position, value)| (*position, *value))
+            .collect();
+        while let Some((position, value)) = queue.pop_min() {
+            for neighbor in self.neighborhood.neighbors(position) {
+                if costs.movement_cost(position, neighbor).is_none() {
+                    continue;
+                }
+                // The map is followed towards its low values, so the step is taken backwards.
+                let cost = match costs.movement_cost(neighbor, position) {
+                    Some(cost) => cost as i64,
+                    None => continue,
+                };
+                let alt = value + cost;
+                if self.get(neighbor).is_none_or(|best| alt < best) {
+                    self.values.insert(neighbor, alt);
+                    if queue.change_priority(&neighbor, alt).is_none() {
+                        queue.push(neighbor, alt);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Creates a map for fleeing from the sources of this map of distances over the given open
+    /// positions, by scaling it by `-factor` and rescanning it. A factor a little above 1, such as
+    /// the traditional 1.2, is enough for units to prefer slipping past a source to being cornered.
+    pub fn fleeing<P: Passable<C> + ?Sized>(&self, open_positions: &P, factor: f64) -> Self
+    where
+        N: Clone,
+    {
+        let mut map = self.clone();
+        map.scale(-factor);
+        map.rescan(&UnitCost(open_positions));
+        map
+    }
+}
+
+#[test]
+fn dijkstra_map_test() {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        pathfinding::{HammingDistance, Heuristic},
+        Position2,
+    };
+
+    // A long corridor leading into a room, with a dead end off to the side of the room.
+    let open_positions: BTreeSet<Position2> = (-30..10)
+        .map(|x| Position2::new(x, 0))
+        .chain((10..15).flat_map(|x| (-2..3).map(move |y| Position2::new(x, y))))
+        .chain((15..20).map(|x| Position2::new(x, 2)))
+        .collect();
+    let sources = [Position2::new(-30, 0), Position2::new(19, 2)];
+    let map = DijkstraMap::new(&open_positions, &sources);
+    assert_eq!(map.len(), open_positions.len());
+    for (position, value) in map.iter() {
+        let nearest = sources
+            .iter()
+            .map(|source| {
+                HammingDistance
+                    .find_shortest_path(&open_positions, position, *source)
+                    .map_or(u64::MAX, |path| path.len() as u64)
+            })
+            .min();
+        assert_eq!(Some(value as u64), nearest);
+        let path = map.roll_downhill(position);
+        assert_eq!(path.len() as i64, value);
+        assert!(sources.contains(path.back().unwrap_or(&position)));
+    }
+
+    // Charging up the costs of the corridor sends everything in the room to the far source.
+    let costs = |_from: Position2, to: Position2| {
+        open_positions
+            .contains(&to)
+            .then_some(if to.x < 10 { 5 } else { 1 })
+    };
+    let cheapest = DijkstraMap::cheapest(&costs, &sources);
+    assert_eq!(cheapest.len(), open_positions.len());
+    assert_eq!(cheapest.get(Position2::new(10, 0)), Some(11));
+    assert_eq!(
+        cheapest.roll_downhill(Position2::new(10, 0)).back(),
+        Some(&sources[1])
+    );
+
+    // Inverting a map of the distance to a unit in the room only leads into a corner of the room,
+    // while fleeing leads past the unit and out along the corridor.
+    let hunter = Position2::new(14, 0);
+    let from_hunter = DijkstraMap::new(&open_positions, &[hunter]);
+    let mut inverted = from_hunter.clone();
+    inverted.invert();
+    let fleeing = from_hunter.fleeing(&open_positions, 1.2);
+    assert_eq!(fleeing.len(), open_positions.len());
+    let prey = Position2::new(14, 2);
+    assert_eq!(
+        inverted.roll_downhill(prey).back(),
+        Some(&Position2::new(10, 2))
+    );
+    assert_eq!(fleeing.roll_downhill(prey).back(), Some(&sources[0]));
+    let mut scaled = from_hunter;
+    scaled.scale(-1.);
+    assert_eq!(scaled, inverted);
+    assert!(DijkstraMap::new(&open_positions, &[Position2::new(0, 5)]).is_empty());
+
+    // A source with nothing open around it is still in the map, however it was built.
+    let island = Position2::new(0, 5);
+    let mut with_island = open_positions.clone();
+    with_island.insert(island);
+    let island_costs = |_from: Position2, to: Position2| with_island.contains(&to).then_some(1);
+    for map in [
+        DijkstraMap::new(&with_island, &[island]),
+        DijkstraMap::cheapest(&island_costs, &[island]),
+    ] {
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(island, 0)]);
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::Sub,
+};
+
+use priority_queue::DoublePriorityQueue;
+
+use crate::{
+    bfs::Bfs,
+    neighborhood::{Neighborhood, VonNeumann},
+    Coordinate, Node, Passable, Position,
+};
+
+/// # Flow Field
+///
+/// The distance from every open position to a single goal, known as the integration field, from
+/// which the direction a unit should step in to get closer to the goal can be read off anywhere on
+/// the map. When many units head for the same rally point, one flow field serves all of them, where
+/// searching for a path for each unit would repeat most of the same work over and over.
+///
+/// The field is built with a [`Bfs`] out from the goal. When [`FlowField::tile_blocked`] or
+/// [`FlowField::tile_opened`] report a change to the map, as described under
+/// [changing maps](crate::pathfinding#changing-maps), only the distances it affects are
+/// recomputed. The [`Neighborhood`] used must be [symmetric](Neighborhood#symmetry).
+pub struct FlowField<C: Node = Position, N = VonNeumann> {
+    neighborhood: N,
+    goal: C,
+    distances: BTreeMap<C, u64>,
+}
+
+impl<C: Coordinate> FlowField<C, VonNeumann> {
+    /// Creates the flow field leading to `goal` over the given open positions.
+    pub fn new<P: Passable<C> + ?Sized>(open_positions: &P, goal: C) -> Self {
+        FlowField::with_neighborhood(open_positions, goal, VonNeumann)
+    }
+}
+
+impl<C: Node, N: Neighborhood<C>> FlowField<C, N> {
+    /// Creates a flow field which moves between positions according to the given
+    /// [`Neighborhood`].
+    pub fn with_neighborhood<P: Passable<C> + ?Sized>(
+        open_positions: &P,
+        goal: C,
+        neighborhood: N,
+    ) -> Self {
+        let distances = Bfs::with_neighborhood(open_positions, goal, &neighborhood).collect();
+        FlowField {
+            neighborhood,
+            goal,
+            distances,
+        }
+    }
+
+    /// The position the field leads to.
+    pub fn goal(&self) -> C {
+        self.goal
+    }
+
+    /// The distance from `position` to the goal, or [`None`] if the goal cannot be reached from
+    /// it.
+    pub fn distance(&self, position: C) -> Option<u64> {
+        self.distances.get(&position).copied()
+    }
+
+    /// All of the positions which can reach the goal along with their distances to it, in the
+    /// order defined on `C`.
+    pub fn iter(&self) -> impl Iterator<Item = (C, u64)> + '_ {
+        self.distances
+            .iter()
+            .map(|(position, distance)| (*position, *distance))
+    }
+
+    /// The neighbor a unit at `position` should step to in order to get one step closer to the
+    /// goal, or [`None`] if it is already there or cannot reach it. Where there are several, the
+    /// first in the order given by the [`Neighborhood`] is chosen.
+    pub fn next_step(&self, position: C) -> Option<C> {
+        let distance = self.distance(position)?.checked_sub(1)?;
+        self.neighborhood
+            .neighbors(position)
+            .find(|neighbor| self.distance(*neighbor) == Some(distance))
+    }
+
+    /// The direction a unit at `position` should step in, as the offset from `position` to
+    /// [`FlowField::next_step`].
+    pub fn direction(&self, position: C) -> Option<C>
+    where
+        C: Sub<Output = C>,
+    {
+        self.next_step(position).map(|next| next - position)
+    }
+
+    /// The path a unit at `position` follows to the goal, which as usual excludes `position` and
+    /// includes the goal.
+    pub fn path_from(&self, position: C) -> Option<VecDeque<C>> {
+        self.distance(position)?;
+        let mut path = VecDeque::new();
+        let mut current_position = position;
+        while let Some(next) = self.next_step(current_position) {
+            path.push_back(next);
+            current_position = next;
+        }
+        Some(path)
+    }
+
+    /// Notifies the field that the given tile has been blocked. Every position whose shortest path
+    /// to the goal went through it has its distance recomputed.
+    pub fn tile_blocked<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, position: C) {
+        if self.distances.remove(&position).is_none() {
+            return;
+        }
+
+        // Positions which no longer have a neighbor one step closer to the goal have lost their
+        // shortest paths, and so may have the positions one step further away which relied on them.
+        let mut removed = Vec::new();
+        let mut unsupported: Vec<C> = self.neighborhood.neighbors(position).collect();
+        while let Some(candidate) = unsupported.pop() {
+            let distance = match self.distance(candidate) {
+                Some(distance) if candidate != self.goal => distance,
+                _ => continue,
+            };
+            let supported = self
+                .neighborhood
+                .neighbors(candidate)
+                .any(|neighbor| self.distance(neighbor) == Some(distance - 1));
+            if !supported {
+                self.distances.remove(&candidate);
+                removed.push(candidate);
+                unsupported.extend(
+                    self.neighborhood
+                        .neighbors(candidate)
+                        .filter(|neighbor| self.distance(*neighbor) == Some(distance + 1)),
+                );
+            }
+        }
+
+        self.repair(open_positions, removed);
+    }
+
+    /// Notifies the field that the given tile has been opened. Every position with a shorter path
+    /// to the goal through it has its distance recomputed.
+    pub fn tile_opened<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, position: C) {
+        self.repair(open_positions, vec![position]);
+    }
+
+    /// Finds distances for the given positions from those of their neighbors, and spreads any
+    /// improvements outwards in order of distance.
+    fn repair<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, positions: Vec<C>) {
+        let mut queue: DoublePriorityQueue<C, u64> = DoublePriorityQueue::new();
+        for position in positions {
+            if !open_positions.is_passable(position) {
+                continue;
+            }
+            let distance = if position == self.goal {
+                Some(0)
+            } else {
+                self.neighborhood
+                    .neighbors(position)
+                    .filter_map(|neighbor| self.distance(neighbor))
+                    .min()
+                    .map(|distance| distance + 1)
+            };
+            if let Some(distance) = distance {
+                if self.distance(position).is_none_or(|best| distance < best) {
+                    self.distances.insert(position, distance);
+                    queue.push(position, distance);
+                }
+            }
+        }
+
+        while let Some((position, distance)) = queue.pop_min() {
+            for neighbor in self.neighborhood.neighbors(position) {
+                if !open_positions.is_passable(neighbor) {
+                    continue;
+                }
+                if self
+                    .distance(neighbor)
+                    .is_none_or(|best| distance + 1 < best)
+                {
+                    self.distances.insert(neighbor, distance + 1);
+                    if queue.change_priority(&neighbor, distance + 1).is_none() {
+                        queue.push(neighbor, distance + 1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn flow_field_test() {
+    use std::collections::BTreeSet;
+
+    use itertools::Itertools;
+
+    use crate::{test_util::assert_valid_path, Position2};
+
+    // A room split by a wall with two gaps in it, and the goal on one side.
+    let mut open_positions: BTreeSet<Position2> = (0..12)
+        .flat_map(|x| (0..12).map(move |y| Position2::new(x, y)))
+        .filter(|position| position.x != 6 || position.y == 2 || position.y == 9)
+        .collect();
+    let goal = Position2::new(10, 5);
+    let mut field = FlowField::new(&open_positions, goal);
+
+    let changes = [
+        Position2::new(6, 2),
+        Position2::new(8, 5),
+        Position2::new(6, 9),
+        Position2::new(6, 5),
+        Position2::new(6, 2),
+        goal,
+        Position2::new(8, 5),
+        goal,
+    ];
+    for position in changes {
+        if open_positions.remove(&position) {
+            field.tile_blocked(&open_positions, position);
+        } else {
+            open_positions.insert(position);
+            field.tile_opened(&open_positions, position);
+        }
+        let expected = FlowField::new(&open_positions, goal);
+        assert!(field.iter().eq(expected.iter()));
+
+        for (position, distance) in field.iter() {
+            let path = field.path_from(position).unwrap();
+            assert_eq!(path.len() as u64, distance);
+            let steps = std::iter::once(position).chain(path.iter().copied());
+            for (from, to) in steps.tuple_windows() {
+                assert_eq!(field.direction(from), Some(to - from));
+            }
+            assert_valid_path(&VonNeumann, &open_positions, position, path, goal);
+        }
+    }
+    assert_eq!(field.path_from(Position2::new(20, 0)), None);
+}
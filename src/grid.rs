@@ -0,0 +1,346 @@
+//! # Grid
+//!
+//! A dense, bounded 3D grid of values stored in a flat [`Vec`], giving constant time lookups by
+//! [`Position`]. A [`Grid3<bool>`] can be used directly as the map of open tiles for the searches
+//! in this crate.
+
+use std::{
+    collections::BTreeSet,
+    ops::{Index, IndexMut},
+};
+
+use crate::{MovementCost, Passable, PassableSet, Position};
+
+/// A dense 3D grid covering every position within an inclusive bounding box, with one value of
+/// type `T` per position. Cells are stored in row-major order with x varying fastest, then y,
+/// then z.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grid3<T> {
+    min: Position,
+    dimensions: (usize, usize, usize),
+    cells: Vec<T>,
+}
+
+impl<T> Grid3<T> {
+    /// Creates a new [`Grid3`] covering all positions between `min` and `max` inclusive, with
+    /// every cell set to `fill`. If `max` is less than `min` along any axis, the grid is empty.
+    /// Panics if the bounding box has more cells than can be counted in a [`usize`].
+    pub fn new(min: Position, max: Position, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        let (dimensions, len) = Self::dimensions_between(min, max);
+        Grid3 {
+            min,
+            dimensions,
+            cells: vec![fill; len],
+        }
+    }
+
+    /// Creates a new [`Grid3`] covering all positions between `min` and `max` inclusive, with
+    /// each cell initialized by calling `f` on its position. Panics if the bounding box is too
+    /// large, as with [`Grid3::new`].
+    pub fn from_fn<F: FnMut(Position) -> T>(min: Position, max: Position, mut f: F) -> Self {
+        let (dimensions, len) = Self::dimensions_between(min, max);
+        let mut grid = Grid3 {
+            min,
+            dimensions,
+            cells: Vec::with_capacity(len),
+        };
+        for index in 0..len {
+            let position = grid.position_of(index);
+            grid.cells.push(f(position));
+        }
+        grid
+    }
+
+    /// The number of cells along each axis of the bounding box from `min` to `max`, along with
+    /// the total number of cells.
+    fn dimensions_between(min: Position, max: Position) -> ((usize, usize, usize), usize) {
+        if max.x < min.x || max.y < min.y || max.z < min.z {
+            return ((0, 0, 0), 0);
+        }
+        fn extent(min: i64, max: i64) -> Option<usize> {
+            usize::try_from(max.abs_diff(min)).ok()?.checked_add(1)
+        }
+        fn size(min: Position, max: Position) -> Option<((usize, usize, usize), usize)> {
+            let dimensions = (
+                extent(min.x, max.x)?,
+                extent(min.y, max.y)?,
+                extent(min.z, max.z)?,
+            );
+            let len = dimensions
+                .0
+                .checked_mul(dimensions.1)?
+                .checked_mul(dimensions.2)?;
+            Some((dimensions, len))
+        }
+        size(min, max).unwrap_or_else(|| {
+            panic!(
+                "a grid from {:?} to {:?} has too many cells to store",
+                min, max
+            )
+        })
+    }
+
+    /// The smallest corner of the bounding box of this grid.
+    pub fn min(&self) -> Position {
+        self.min
+    }
+
+    /// The largest corner of the bounding box of this grid, or [`None`] if the grid is empty.
+    pub fn max(&self) -> Option<Position> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(
+                self.min
+                    + Position::new(
+                        self.dimensions.0 as i64 - 1,
+                        self.dimensions.1 as i64 - 1,
+                        self.dimensions.2 as i64 - 1,
+                    ),
+            )
+        }
+    }
+
+    /// The number of cells along the x, y and z axes respectively.
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        self.dimensions
+    }
+
+    /// The total number of cells in this grid.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns whether or not this grid has no cells at all.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Returns whether or not the given position lies within the bounds of this grid.
+    pub fn contains(&self, position: Position) -> bool {
+        self.index_of(position).is_some()
+    }
+
+    fn index_of(&self, position: Position) -> Option<usize> {
+        let offset = position - self.min;
+        let (x, y, z) = (offset.x as u64, offset.y as u64, offset.z as u64);
+        let (dx, dy, dz) = self.dimensions;
+        if x < dx as u64 && y < dy as u64 && z < dz as u64 {
+            Some((z as usize * dy + y as usize) * dx + x as usize)
+        } else {
+            None
+        }
+    }
+
+    fn position_of(&self, index: usize) -> Position {
+        let (dx, dy, _) = self.dimensions;
+        self.min
+            + Position::new(
+                (index % dx) as i64,
+                ((index / dx) % dy) as i64,
+                (index / (dx * dy)) as i64,
+            )
+    }
+
+    /// Returns a reference to the value at the given position, or [`None`] if it is out of
+    /// bounds.
+    pub fn get(&self, position: Position) -> Option<&T> {
+        self.index_of(position).map(|index| &self.cells[index])
+    }
+
+    /// Returns a mutable reference to the value at the given position, or [`None`] if it is out
+    /// of bounds.
+    pub fn get_mut(&mut self, position: Position) -> Option<&mut T> {
+        self.index_of(position)
+            .map(move |index| &mut self.cells[index])
+    }
+
+    /// Sets the value at the given position, returning the previous value. If the position is out
+    /// of bounds, the grid is left unchanged and [`None`] is returned.
+    pub fn set(&mut self, position: Position, value: T) -> Option<T> {
+        self.get_mut(position)
+            .map(|cell| std::mem::replace(cell, value))
+    }
+
+    /// Returns an iterator over every position in this grid alongside its value.
+    pub fn iter(&self) -> impl Iterator<Item = (Position, &T)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(index, cell)| (self.position_of(index), cell))
+    }
+
+    /// Returns an iterator over every position in this grid alongside a mutable reference to its
+    /// value.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Position, &mut T)> + '_ {
+        let (min, (dx, dy, _)) = (self.min, self.dimensions);
+        self.cells.iter_mut().enumerate().map(move |(index, cell)| {
+            let offset = Position::new(
+                (index % dx) as i64,
+                ((index / dx) % dy) as i64,
+                (index / (dx * dy)) as i64,
+            );
+            (min + offset, cell)
+        })
+    }
+
+    /// Returns an iterator over every position in this grid.
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.cells.len()).map(move |index| self.position_of(index))
+    }
+}
+
+impl Grid3<bool> {
+    /// Returns the set of all positions in this grid which are marked as open.
+    pub fn open_positions(&self) -> BTreeSet<Position> {
+        self.passable_positions().collect()
+    }
+}
+
+impl<T> Index<Position> for Grid3<T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &Self::Output {
+        self.get(position)
+            .expect("position out of the bounds of the grid")
+    }
+}
+
+impl<T> IndexMut<Position> for Grid3<T> {
+    fn index_mut(&mut self, position: Position) -> &mut Self::Output {
+        self.get_mut(position)
+            .expect("position out of the bounds of the grid")
+    }
+}
+
+impl Passable for Grid3<bool> {
+    fn is_passable(&self, position: Position) -> bool {
+        self.get(position).copied().unwrap_or(false)
+    }
+}
+
+impl PassableSet for Grid3<bool> {
+    fn passable_positions(&self) -> Box<dyn Iterator<Item = Position> + '_> {
+        Box::new(
+            self.iter()
+                .filter(|(_, open)| **open)
+                .map(|(position, _)| position),
+        )
+    }
+}
+
+/// Each cell holds the cost of stepping onto it, or [`None`] if it is blocked.
+impl MovementCost for Grid3<Option<u64>> {
+    fn movement_cost(&self, _from: Position, to: Position) -> Option<u64> {
+        self.get(to).copied().flatten()
+    }
+}
+
+/// Builds the smallest grid containing every position in the set, marking exactly those positions
+/// as open.
+impl From<&BTreeSet<Position>> for Grid3<bool> {
+    fn from(open_positions: &BTreeSet<Position>) -> Self {
+        let (min, max) = match (open_positions.first(), open_positions.last()) {
+            (Some(first), Some(_)) => {
+                open_positions
+                    .iter()
+                    .fold((*first, *first), |(min, max), position| {
+                        (
+                            Position::new(
+                                min.x.min(position.x),
+                                min.y.min(position.y),
+                                min.z.min(position.z),
+                            ),
+                            Position::new(
+                                max.x.max(position.x),
+                                max.y.max(position.y),
+                                max.z.max(position.z),
+                            ),
+                        )
+                    })
+            }
+            _ => (Position::new(0, 0, 0), Position::new(-1, -1, -1)),
+        };
+        let mut grid = Grid3::new(min, max, false);
+        for position in open_positions.iter().copied() {
+            grid[position] = true;
+        }
+        grid
+    }
+}
+
+impl From<&Grid3<bool>> for BTreeSet<Position> {
+    fn from(grid: &Grid3<bool>) -> Self {
+        grid.open_positions()
+    }
+}
+
+#[test]
+fn grid_test() {
+    let mut grid = Grid3::new(Position::new(-1, 0, 2), Position::new(1, 1, 2), 0u8);
+    assert_eq!(grid.dimensions(), (3, 2, 1));
+    assert_eq!(grid.len(), 6);
+    assert_eq!(grid.max(), Some(Position::new(1, 1, 2)));
+    assert_eq!(grid.set(Position::new(0, 1, 2), 7), Some(0));
+    assert_eq!(grid.set(Position::new(0, 1, 3), 7), None);
+    assert_eq!(grid.get(Position::new(0, 1, 2)), Some(&7));
+    assert_eq!(grid.get(Position::new(2, 1, 2)), None);
+    assert_eq!(
+        grid.positions().collect::<Vec<_>>(),
+        grid.iter()
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>()
+    );
+    assert!(grid.positions().all(|position| grid.contains(position)));
+
+    let empty = Grid3::new(Position::new(0, 0, 0), Position::new(3, -1, 3), ());
+    assert!(empty.is_empty());
+    assert_eq!(empty.max(), None);
+
+    let open_positions: BTreeSet<Position> = vec![
+        Position::new(0, 0, 0),
+        Position::new(2, 0, 0),
+        Position::new(2, 3, -1),
+    ]
+    .into_iter()
+    .collect();
+    let grid = Grid3::from(&open_positions);
+    assert_eq!(grid.min(), Position::new(0, 0, -1));
+    assert_eq!(grid.max(), Some(Position::new(2, 3, 0)));
+    assert!(grid.is_passable(Position::new(2, 3, -1)));
+    assert!(!grid.is_passable(Position::new(1, 0, 0)));
+    assert!(!grid.is_passable(Position::new(5, 5, 5)));
+    assert_eq!(BTreeSet::from(&grid), open_positions);
+
+    use crate::pathfinding::{HammingDistance, Heuristic};
+
+    let grid = Grid3::from_fn(Position::new(0, 0, 0), Position::new(4, 4, 0), |position| {
+        position.x != 2 || position.y == 4
+    });
+    assert_eq!(
+        crate::bfs::Bfs::new(&grid, Position::new(0, 0, 0)).last(),
+        Some((Position::new(4, 0, 0), 12))
+    );
+    assert_eq!(
+        HammingDistance
+            .find_shortest_path(&grid, Position::new(0, 0, 0), Position::new(4, 0, 0))
+            .map(|path| path.len()),
+        Some(12)
+    );
+}
+
+#[test]
+#[should_panic(expected = "too many cells")]
+fn oversized_grid_test() {
+    let far_apart: BTreeSet<Position> = [
+        Position::new(0, 0, 0),
+        Position::new(1 << 22, 1 << 22, 1 << 22),
+    ]
+    .into_iter()
+    .collect();
+    let _ = Grid3::from(&far_apart);
+}
@@ -0,0 +1,253 @@
+//! # Hex
+//!
+//! Coordinates on a hexagonal grid, stored in axial form, along with the conversions needed to
+//! draw them. The third cube coordinate is always `s = -q - r`.
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::Coordinate;
+
+/// Represents a hexagon in a hexagonal grid using axial coordinates. Around the bounds of an
+/// [`i64`], all arithmetic in this module will be done in a wrapping way, just like for
+/// [`Position`](crate::Position).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Component))]
+pub struct Hex {
+    pub q: i64,
+    pub r: i64,
+}
+
+impl From<(i64, i64)> for Hex {
+    fn from(pair: (i64, i64)) -> Self {
+        Hex {
+            q: pair.0,
+            r: pair.1,
+        }
+    }
+}
+
+impl Hex {
+    /// The offsets to each of the six neighbors of a hex, going counterclockwise starting from the
+    /// direction of increasing `q`.
+    pub const DIRECTIONS: [Hex; 6] = [
+        Hex { q: 1, r: 0 },
+        Hex { q: 1, r: -1 },
+        Hex { q: 0, r: -1 },
+        Hex { q: -1, r: 0 },
+        Hex { q: -1, r: 1 },
+        Hex { q: 0, r: 1 },
+    ];
+
+    /// Creates a new [`Hex`] from axial coordinates.
+    pub fn new(q: i64, r: i64) -> Self {
+        Hex { q, r }
+    }
+
+    /// The third cube coordinate, such that `q + r + s == 0`.
+    pub fn s(self) -> i64 {
+        self.q.wrapping_neg().wrapping_sub(self.r)
+    }
+
+    /// Returns whether or not we are adjacent to another hex.
+    pub fn is_adjacent_to(self, other: Hex) -> bool {
+        self.hex_distance(other) == 1
+    }
+
+    /// Returns an iterator over all six adjacent hexes, in the order of [`Hex::DIRECTIONS`].
+    pub fn adjacent(self) -> Box<dyn Iterator<Item = Hex>> {
+        Box::new(
+            Hex::DIRECTIONS
+                .into_iter()
+                .map(move |direction| self + direction),
+        )
+    }
+
+    /// Computes the number of steps between adjacent hexes it takes to get from this hex to the
+    /// other one:
+    /// ```
+    /// use positioning::hex::Hex;
+    ///
+    /// assert_eq!(Hex::new(0, 0).hex_distance(Hex::new(2, -3)), 3);
+    /// ```
+    pub fn hex_distance(self, other: Hex) -> u64 {
+        (self.q.abs_diff(other.q) + self.r.abs_diff(other.r) + self.s().abs_diff(other.s())) / 2
+    }
+
+    /// Returns an iterator over all hexes at exactly the given distance from this one, going
+    /// counterclockwise. A ring of radius 0 is just this hex.
+    pub fn ring(self, radius: u64) -> Box<dyn Iterator<Item = Hex>> {
+        if radius == 0 {
+            return Box::new(std::iter::once(self));
+        }
+        let start = self + Hex::DIRECTIONS[4] * radius as i64;
+        Box::new(
+            Hex::DIRECTIONS
+                .into_iter()
+                .flat_map(move |direction| std::iter::repeat_n(direction, radius as usize))
+                .scan(start, |hex, direction| {
+                    let current = *hex;
+                    *hex = current + direction;
+                    Some(current)
+                }),
+        )
+    }
+
+    /// Returns an iterator over all hexes within the given distance from this one, starting from
+    /// this hex and moving outwards one ring at a time.
+    pub fn spiral(self, radius: u64) -> Box<dyn Iterator<Item = Hex>> {
+        Box::new((0..=radius).flat_map(move |ring| self.ring(ring)))
+    }
+
+    /// Rounds fractional axial coordinates to the hex containing them.
+    pub fn round(q: f64, r: f64) -> Hex {
+        let s = -q - r;
+        let (mut rounded_q, mut rounded_r, rounded_s) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = (
+            (rounded_q - q).abs(),
+            (rounded_r - r).abs(),
+            (rounded_s - s).abs(),
+        );
+        if dq > dr && dq > ds {
+            rounded_q = -rounded_r - rounded_s;
+        } else if dr > ds {
+            rounded_r = -rounded_q - rounded_s;
+        }
+        Hex::new(rounded_q as i64, rounded_r as i64)
+    }
+}
+
+impl Coordinate for Hex {
+    fn adjacent(self) -> Box<dyn Iterator<Item = Self>> {
+        Hex::adjacent(self)
+    }
+
+    fn distance(self, other: Self) -> u64 {
+        self.hex_distance(other)
+    }
+}
+
+/// Whether hexes are drawn with a corner or a flat edge at the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HexOrientation {
+    Pointy,
+    Flat,
+}
+
+/// Describes how hexes are laid out in pixel space, so that they can be converted to and from
+/// pixel coordinates. The `size` is the distance from the center of a hex to any of its corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexLayout {
+    pub orientation: HexOrientation,
+    pub size: f64,
+    pub origin: (f64, f64),
+}
+
+impl HexLayout {
+    /// Returns the pixel coordinates of the center of the given hex.
+    pub fn to_pixel(&self, hex: Hex) -> (f64, f64) {
+        let sqrt_3 = 3f64.sqrt();
+        let (q, r) = (hex.q as f64, hex.r as f64);
+        let (x, y) = match self.orientation {
+            HexOrientation::Pointy => (sqrt_3 * q + sqrt_3 / 2. * r, 1.5 * r),
+            HexOrientation::Flat => (1.5 * q, sqrt_3 / 2. * q + sqrt_3 * r),
+        };
+        (x * self.size + self.origin.0, y * self.size + self.origin.1)
+    }
+
+    /// Returns the hex containing the given pixel coordinates.
+    pub fn from_pixel(&self, pixel: (f64, f64)) -> Hex {
+        let sqrt_3 = 3f64.sqrt();
+        let x = (pixel.0 - self.origin.0) / self.size;
+        let y = (pixel.1 - self.origin.1) / self.size;
+        let (q, r) = match self.orientation {
+            HexOrientation::Pointy => (sqrt_3 / 3. * x - y / 3., 2. / 3. * y),
+            HexOrientation::Flat => (2. / 3. * x, -x / 3. + sqrt_3 / 3. * y),
+        };
+        Hex::round(q, r)
+    }
+}
+
+#[test]
+fn hex_test() {
+    let origin = Hex::new(0, 0);
+    let a = Hex::new(2, -1);
+
+    assert_eq!(a.adjacent().count(), 6);
+    for neighbor in a.adjacent() {
+        assert!(a.is_adjacent_to(neighbor));
+        assert_eq!(neighbor.q + neighbor.r + neighbor.s(), 0);
+    }
+    assert!(!origin.is_adjacent_to(Hex::new(1, 1)));
+    assert_eq!(origin.hex_distance(a), 2);
+
+    for radius in 0..5u64 {
+        let ring: Vec<Hex> = a.ring(radius).collect();
+        assert_eq!(ring.len() as u64, if radius == 0 { 1 } else { 6 * radius });
+        assert!(ring.iter().all(|hex| hex.hex_distance(a) == radius));
+        assert_eq!(
+            a.spiral(radius).count() as u64,
+            1 + 3 * radius * (radius + 1)
+        );
+    }
+
+    assert_eq!(Hex::round(0.4, 0.4), Hex::new(0, 1));
+    assert_eq!(Hex::round(-0.2, 0.1), origin);
+    assert_eq!(Hex::round(1.6, -0.8), Hex::new(2, -1));
+
+    for orientation in [HexOrientation::Pointy, HexOrientation::Flat] {
+        let layout = HexLayout {
+            orientation,
+            size: 10.,
+            origin: (5., -3.),
+        };
+        assert_eq!(layout.to_pixel(origin), (5., -3.));
+        for hex in origin.spiral(4) {
+            let (x, y) = layout.to_pixel(hex);
+            assert_eq!(layout.from_pixel((x, y)), hex);
+            assert_eq!(layout.from_pixel((x + 4., y - 4.)), hex);
+        }
+    }
+}
+
+impl Add<Hex> for Hex {
+    type Output = Hex;
+
+    fn add(self, rhs: Hex) -> Self::Output {
+        Hex {
+            q: self.q.wrapping_add(rhs.q),
+            r: self.r.wrapping_add(rhs.r),
+        }
+    }
+}
+
+impl Sub<Hex> for Hex {
+    type Output = Hex;
+
+    fn sub(self, rhs: Hex) -> Self::Output {
+        Hex {
+            q: self.q.wrapping_sub(rhs.q),
+            r: self.r.wrapping_sub(rhs.r),
+        }
+    }
+}
+
+impl Mul<i64> for Hex {
+    type Output = Hex;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        Hex {
+            q: self.q.wrapping_mul(rhs),
+            r: self.r.wrapping_mul(rhs),
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand::distributions::Distribution<Hex> for rand::distributions::Standard {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Hex {
+        Hex::new(rng.gen(), rng.gen())
+    }
+}
@@ -0,0 +1,396 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    ops::RangeInclusive,
+};
+
+use itertools::iproduct;
+
+use crate::{
+    bfs::Bfs,
+    neighborhood::Neighborhood,
+    pathfinding::{HammingDistance, Heuristic},
+    MovementCost, Passable, PassableSet, Position,
+};
+
+/// # Hierarchical Pathfinding
+///
+/// An implementation of HPA*, which splits the map into fixed size clusters and precomputes an
+/// abstract graph whose nodes are the entrances between neighboring clusters. Each entrance is
+/// connected to the entrances it leads to in the neighboring cluster, and to the other entrances of
+/// its own cluster which can be reached without leaving it, labelled with the distances between
+/// them. Searches run over this much smaller graph first, and the abstract path is then refined
+/// into a concrete one by searching within one cluster at a time.
+///
+/// Paths are found with [`Position::adjacent`] movement and are not always the shortest, but
+/// are usually close to it. Unlike [`AllPairsShortestPaths`](crate::pathfinding::AllPairsShortestPaths),
+/// the precomputation grows linearly with the size of the map, and when tiles are opened or
+/// blocked only the clusters around them need to be rebuilt with
+/// [`HierarchicalMap::tile_changed`], as described under
+/// [changing maps](crate::pathfinding#changing-maps).
+pub struct HierarchicalMap {
+    cluster_size: (i64, i64, i64),
+    clusters: HashMap<Position, Cluster>,
+}
+
+/// The entrances of a single cluster and the paths between them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Cluster {
+    /// Each entrance, along with the entrances of neighboring clusters it leads to in one step.
+    entrances: BTreeMap<Position, Vec<Position>>,
+    /// For each entrance, the other entrances reachable within the cluster and their distances.
+    paths: BTreeMap<Position, Vec<(Position, u64)>>,
+}
+
+impl HierarchicalMap {
+    /// Splits the open positions into clusters of the given size along each axis and computes the
+    /// abstract graph between them.
+    pub fn new<P: PassableSet + ?Sized>(open_positions: &P, cluster_size: (i64, i64, i64)) -> Self {
+        assert!(
+            cluster_size.0 > 0 && cluster_size.1 > 0 && cluster_size.2 > 0,
+            "clusters must be nonempty"
+        );
+        let mut map = HierarchicalMap {
+            cluster_size,
+            clusters: HashMap::new(),
+        };
+        let keys: BTreeSet<Position> = open_positions
+            .passable_positions()
+            .map(|position| map.cluster_of(position))
+            .collect();
+        for key in keys {
+            map.rebuild_cluster(open_positions, key);
+        }
+        map
+    }
+
+    /// Returns the key of the cluster containing the given position, which is its position in the
+    /// grid of clusters.
+    pub fn cluster_of(&self, position: Position) -> Position {
+        Position::new(
+            position.x.div_euclid(self.cluster_size.0),
+            position.y.div_euclid(self.cluster_size.1),
+            position.z.div_euclid(self.cluster_size.2),
+        )
+    }
+
+    /// The number of clusters containing at least one open position.
+    pub fn cluster_count(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Returns an iterator over every entrance in the abstract graph.
+    pub fn entrances(&self) -> impl Iterator<Item = Position> + '_ {
+        self.clusters
+            .values()
+            .flat_map(|cluster| cluster.entrances.keys().copied())
+    }
+
+    /// Updates the abstract graph after the given tile has been opened or blocked. Only the cluster
+    /// containing the tile and any neighboring clusters sharing a face with it are rebuilt.
+    pub fn tile_changed<P: Passable + ?Sized>(&mut self, open_positions: &P, position: Position) {
+        let keys: BTreeSet<Position> = std::iter::once(position)
+            .chain(position.adjacent())
+            .map(|position| self.cluster_of(position))
+            .collect();
+        for key in keys {
+            self.rebuild_cluster(open_positions, key);
+        }
+    }
+
+    /// Finds a path from `start` to `end` by searching the abstract graph and then refining each
+    /// step of the abstract path within a single cluster. As with
+    /// [`Heuristic::find_shortest_path`], the path excludes `start` and includes `end`.
+    pub fn find_path<P: Passable + ?Sized>(
+        &self,
+        open_positions: &P,
+        start: Position,
+        end: Position,
+    ) -> Option<VecDeque<Position>> {
+        if !open_positions.is_passable(start) || !open_positions.is_passable(end) {
+            return None;
+        }
+        if start == end {
+            return Some(VecDeque::new());
+        }
+
+        let (start_key, end_key) = (self.cluster_of(start), self.cluster_of(end));
+        let within_start = self.within_cluster(open_positions, start_key);
+        let local: BTreeMap<Position, u64> = Bfs::new(&within_start, start).collect();
+        let start_paths = self.paths_to_entrances(start_key, &local);
+        let direct = if start_key == end_key {
+            local.get(&end).copied()
+        } else {
+            None
+        };
+        let within_end = self.within_cluster(open_positions, end_key);
+        let local: BTreeMap<Position, u64> = Bfs::new(&within_end, end).collect();
+        let end_paths = self.paths_to_entrances(end_key, &local);
+
+        let graph = AbstractGraph {
+            map: self,
+            start,
+            end,
+            start_paths,
+            end_paths,
+            direct,
+        };
+        let (abstract_path, _cost) =
+            HammingDistance.find_cheapest_path_with(&graph, start, end, &graph)?;
+
+        let mut path = VecDeque::new();
+        let mut from = start;
+        for to in abstract_path {
+            let key = self.cluster_of(from);
+            if key != self.cluster_of(to) {
+                path.push_back(to);
+            } else {
+                let within = self.within_cluster(open_positions, key);
+                path.extend(HammingDistance.find_shortest_path(&within, from, to)?);
+            }
+            from = to;
+        }
+        Some(path)
+    }
+
+    fn within_cluster<'a, P: Passable + ?Sized>(
+        &self,
+        open_positions: &'a P,
+        key: Position,
+    ) -> impl Fn(Position) -> bool + 'a {
+        let cluster_size = self.cluster_size;
+        move |position: Position| {
+            Position::new(
+                position.x.div_euclid(cluster_size.0),
+                position.y.div_euclid(cluster_size.1),
+                position.z.div_euclid(cluster_size.2),
+            ) == key
+                && open_positions.is_passable(position)
+        }
+    }
+
+    fn paths_to_entrances(
+        &self,
+        key: Position,
+        distances: &BTreeMap<Position, u64>,
+    ) -> BTreeMap<Position, u64> {
+        self.clusters
+            .get(&key)
+            .map(|cluster| {
+                cluster
+                    .entrances
+                    .keys()
+                    .filter_map(|entrance| Some((*entrance, *distances.get(entrance)?)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn positions_in(&self, key: Position) -> impl Iterator<Item = Position> {
+        // Clusters at the very edges of the coordinate space are cut short rather than overflowing.
+        fn span(key: i64, size: i64) -> RangeInclusive<i64> {
+            let min = (key as i128 * size as i128).max(i64::MIN as i128);
+            let max = ((key as i128 + 1) * size as i128 - 1).min(i64::MAX as i128);
+            min as i64..=max as i64
+        }
+        let (sx, sy, sz) = self.cluster_size;
+        iproduct!(span(key.x, sx), span(key.y, sy), span(key.z, sz))
+            .map(|(x, y, z)| Position::new(x, y, z))
+    }
+
+    /// Recomputes the entrances of the given cluster and the paths between them. Entrances are
+    /// chosen from each maximal connected run of open positions facing open positions in a
+    /// neighboring cluster, taking the middle one of each run. This only depends on the tiles on
+    /// either side of the face, so both clusters sharing a face always agree on its entrances.
+    fn rebuild_cluster<P: Passable + ?Sized>(&mut self, open_positions: &P, key: Position) {
+        let open: Vec<Position> = self
+            .positions_in(key)
+            .filter(|position| open_positions.is_passable(*position))
+            .collect();
+        if open.is_empty() {
+            self.clusters.remove(&key);
+            return;
+        }
+
+        let mut faces: BTreeMap<Position, BTreeSet<Position>> = BTreeMap::new();
+        for position in open.iter().copied() {
+            for neighbor in position.adjacent() {
+                if self.cluster_of(neighbor) != key && open_positions.is_passable(neighbor) {
+                    faces
+                        .entry(neighbor - position)
+                        .or_default()
+                        .insert(position);
+                }
+            }
+        }
+
+        let mut cluster = Cluster::default();
+        for (direction, mut face) in faces {
+            while let Some(first) = face.first().copied() {
+                let mut run: Vec<Position> = Bfs::new(&face, first)
+                    .map(|(position, _distance)| position)
+                    .collect();
+                for position in run.iter() {
+                    face.remove(position);
+                }
+                run.sort();
+                let entrance = run[run.len() / 2];
+                cluster
+                    .entrances
+                    .entry(entrance)
+                    .or_default()
+                    .push(entrance + direction);
+            }
+        }
+
+        let within = self.within_cluster(open_positions, key);
+        for entrance in cluster.entrances.keys().copied() {
+            let paths = Bfs::new(&within, entrance)
+                .filter(|(position, _distance)| {
+                    *position != entrance && cluster.entrances.contains_key(position)
+                })
+                .collect();
+            cluster.paths.insert(entrance, paths);
+        }
+        self.clusters.insert(key, cluster);
+    }
+}
+
+/// The abstract graph of a [`HierarchicalMap`] during a single search, with the start and end
+/// temporarily connected to the entrances of their clusters.
+struct AbstractGraph<'a> {
+    map: &'a HierarchicalMap,
+    start: Position,
+    end: Position,
+    start_paths: BTreeMap<Position, u64>,
+    end_paths: BTreeMap<Position, u64>,
+    direct: Option<u64>,
+}
+
+impl<'a> AbstractGraph<'a> {
+    fn cluster(&self, position: Position) -> Option<&'a Cluster> {
+        self.map.clusters.get(&self.map.cluster_of(position))
+    }
+}
+
+impl<'a> Neighborhood for AbstractGraph<'a> {
+    fn neighbors(&self, position: Position) -> Box<dyn Iterator<Item = Position>> {
+        let mut neighbors = Vec::new();
+        if position == self.start {
+            neighbors.extend(self.start_paths.keys().copied());
+            if self.direct.is_some() {
+                neighbors.push(self.end);
+            }
+        }
+        if let Some(cluster) = self.cluster(position) {
+            if let Some(partners) = cluster.entrances.get(&position) {
+                neighbors.extend(partners.iter().copied());
+            }
+            if let Some(paths) = cluster.paths.get(&position) {
+                neighbors.extend(paths.iter().map(|(entrance, _distance)| *entrance));
+            }
+        }
+        if self.end_paths.contains_key(&position) {
+            neighbors.push(self.end);
+        }
+        Box::new(neighbors.into_iter())
+    }
+}
+
+impl<'a> MovementCost for AbstractGraph<'a> {
+    fn movement_cost(&self, from: Position, to: Position) -> Option<u64> {
+        let mut costs = Vec::new();
+        if from == self.start {
+            costs.extend(self.start_paths.get(&to).copied());
+            if to == self.end {
+                costs.extend(self.direct);
+            }
+        }
+        if to == self.end {
+            costs.extend(self.end_paths.get(&from).copied());
+        }
+        if let Some(cluster) = self.cluster(from) {
+            if let Some(partners) = cluster.entrances.get(&from) {
+                if partners.contains(&to) {
+                    costs.push(1);
+                }
+            }
+            if let Some(paths) = cluster.paths.get(&from) {
+                costs.extend(
+                    paths
+                        .iter()
+                        .filter(|(entrance, _distance)| *entrance == to)
+                        .map(|(_entrance, distance)| *distance),
+                );
+            }
+        }
+        costs.into_iter().min()
+    }
+}
+
+#[test]
+fn hierarchical_test() {
+    use itertools::Itertools;
+
+    use crate::{neighborhood::VonNeumann, test_util::assert_valid_path};
+
+    let mut open_positions: BTreeSet<Position> = (0..24)
+        .cartesian_product(0..24)
+        .cartesian_product(0..2)
+        .filter(|((x, y), z)| {
+            let wall = (x % 8 == 3 && y % 8 != 6) || (y % 8 == 5 && x % 8 != 1);
+            !wall || (*z == 1 && (x + y) % 5 == 0)
+        })
+        .map(|((x, y), z)| Position::new(x, y, z))
+        .collect();
+    let mut map = HierarchicalMap::new(&open_positions, (6, 6, 2));
+    assert_eq!(map.cluster_count(), 16);
+    assert!(map.entrances().count() > 0);
+
+    let check = |map: &HierarchicalMap, open_positions: &BTreeSet<Position>| {
+        let start = Position::new(0, 0, 0);
+        for end in open_positions.iter().copied().step_by(7) {
+            let optimal = HammingDistance.find_shortest_path(open_positions, start, end);
+            let found = map.find_path(open_positions, start, end);
+            assert_eq!(found.is_some(), optimal.is_some());
+            if let (Some(found), Some(optimal)) = (found, optimal) {
+                assert!(found.len() >= optimal.len());
+                assert_valid_path(&VonNeumann, open_positions, start, found, end);
+            }
+        }
+    };
+    check(&map, &open_positions);
+    assert_eq!(
+        map.find_path(
+            &open_positions,
+            Position::new(1, 1, 0),
+            Position::new(1, 1, 0)
+        ),
+        Some(VecDeque::new())
+    );
+
+    // Rebuilding only the clusters around changed tiles agrees with building from scratch.
+    for position in [
+        Position::new(5, 2, 0),
+        Position::new(6, 2, 0),
+        Position::new(11, 11, 1),
+        Position::new(12, 0, 0),
+    ] {
+        if !open_positions.remove(&position) {
+            open_positions.insert(position);
+        }
+        map.tile_changed(&open_positions, position);
+    }
+    assert!(map.clusters == HierarchicalMap::new(&open_positions, (6, 6, 2)).clusters);
+    check(&map, &open_positions);
+
+    // The cluster at the very edge of the coordinate space would start before i64::MIN.
+    let edge: BTreeSet<Position> = (1..4).map(|x| Position::new(i64::MIN + x, 0, 0)).collect();
+    let edge_map = HierarchicalMap::new(&edge, (6, 6, 2));
+    let (first, last) = (*edge.first().unwrap(), *edge.last().unwrap());
+    assert_eq!(
+        edge_map
+            .find_path(&edge, first, last)
+            .map(|path| path.len()),
+        Some(2)
+    );
+}
@@ -0,0 +1,370 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use priority_queue::DoublePriorityQueue;
+
+use crate::{
+    neighborhood::{Neighborhood, VonNeumann},
+    pathfinding::{Heuristic, SearchStats, WithInfinity},
+    Coordinate, Node, Passable, Position,
+};
+
+/// The priority of a position in the queue of an [`IncrementalPlanner`].
+type Key = (WithInfinity<u64>, WithInfinity<u64>);
+
+/// # Incremental Planner
+///
+/// An implementation of D* Lite, which finds the shortest path from a start to a fixed goal and
+/// then keeps the results of its search around, so that when tiles are blocked or opened only the
+/// parts of the search they affect need to be redone. The start may also move along the path, as a
+/// unit following it would, without invalidating anything. This makes it well suited to the
+/// dynamic blockers described in the [`pathfinding`](crate::pathfinding) module, and it works
+/// with the same heuristics, including
+/// [`AllPairsShortestPaths`](crate::pathfinding::AllPairsShortestPaths) under the same conditions.
+///
+/// The search runs backwards from the goal, so the [`Neighborhood`] used must be
+/// [symmetric](Neighborhood#symmetry). The heuristic must also be consistent, as all of the
+/// heuristics in this crate are when used as documented, since the priorities of positions in the
+/// queue are only ever adjusted by the heuristic distance the start has moved.
+///
+/// Changes to the map are reported with [`IncrementalPlanner::tile_blocked`] and
+/// [`IncrementalPlanner::tile_opened`], as described under
+/// [changing maps](crate::pathfinding#changing-maps).
+pub struct IncrementalPlanner<H, C: Node = Position, N = VonNeumann> {
+    heuristic: H,
+    neighborhood: N,
+    start: C,
+    goal: C,
+    key_modifier: u64,
+    distances: BTreeMap<C, WithInfinity<u64>>,
+    lookahead: BTreeMap<C, WithInfinity<u64>>,
+    queue: DoublePriorityQueue<C, Key>,
+    stats: SearchStats,
+}
+
+impl<H: Heuristic<C>, C: Coordinate> IncrementalPlanner<H, C, VonNeumann> {
+    /// Creates a planner for paths from `start` to `goal`, guided by the given heuristic.
+    pub fn new(heuristic: H, start: C, goal: C) -> Self {
+        IncrementalPlanner::with_neighborhood(heuristic, start, goal, VonNeumann)
+    }
+}
+
+impl<H: Heuristic<C>, C: Node, N: Neighborhood<C>> IncrementalPlanner<H, C, N> {
+    /// Creates a planner which moves between positions according to the given [`Neighborhood`].
+    pub fn with_neighborhood(heuristic: H, start: C, goal: C, neighborhood: N) -> Self {
+        let mut planner = IncrementalPlanner {
+            heuristic,
+            neighborhood,
+            start,
+            goal,
+            key_modifier: 0,
+            distances: BTreeMap::new(),
+            lookahead: BTreeMap::new(),
+            queue: DoublePriorityQueue::new(),
+            stats: SearchStats::default(),
+        };
+        planner.reset();
+        planner
+    }
+
+    /// The position paths are currently planned from.
+    pub fn start(&self) -> C {
+        self.start
+    }
+
+    /// The position paths are planned to.
+    pub fn goal(&self) -> C {
+        self.goal
+    }
+
+    /// The work done by the planner so far, over all of the searches and repairs it has run.
+    pub fn stats(&self) -> SearchStats {
+        self.stats
+    }
+
+    /// Moves the start of the planned paths, which is usually done as a unit steps along them.
+    /// The results of previous searches remain valid from the new start.
+    pub fn move_to(&mut self, start: C) {
+        match self.heuristic.heuristic_distance(self.start, start) {
+            WithInfinity::Normal(distance) => {
+                self.key_modifier += distance;
+                self.start = start;
+            }
+            WithInfinity::Infinity => {
+                self.start = start;
+                self.reset();
+            }
+        }
+    }
+
+    /// Notifies the planner that the given tile has been blocked.
+    pub fn tile_blocked<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, position: C) {
+        self.tile_changed(open_positions, position);
+    }
+
+    /// Notifies the planner that the given tile has been opened.
+    pub fn tile_opened<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, position: C) {
+        self.tile_changed(open_positions, position);
+    }
+
+    /// Finds the shortest path from the start to the goal, repairing the previous search as
+    /// needed. As with [`Heuristic::find_shortest_path`], the path excludes the start and includes
+    /// the goal.
+    pub fn find_path<P: Passable<C> + ?Sized>(
+        &mut self,
+        open_positions: &P,
+    ) -> Option<VecDeque<C>> {
+        if !open_positions.is_passable(self.goal) {
+            return None;
+        }
+        self.compute_shortest_path(open_positions);
+
+        let mut remaining = match self.distance(self.start) {
+            WithInfinity::Infinity => return None,
+            WithInfinity::Normal(distance) => distance,
+        };
+        let mut path = VecDeque::new();
+        let mut current_position = self.start;
+        while current_position != self.goal {
+            let (next, distance) = self
+                .neighborhood
+                .neighbors(current_position)
+                .filter(|neighbor| open_positions.is_passable(*neighbor))
+                .map(|neighbor| (neighbor, self.distance(neighbor)))
+                .min_by_key(|(_, distance)| *distance)?;
+            match distance {
+                WithInfinity::Normal(distance) if distance < remaining => remaining = distance,
+                _ => return None,
+            }
+            path.push_back(next);
+            current_position = next;
+        }
+        Some(path)
+    }
+
+    fn reset(&mut self) {
+        self.key_modifier = 0;
+        self.distances.clear();
+        self.lookahead.clear();
+        self.queue.clear();
+        self.lookahead.insert(self.goal, WithInfinity::Normal(0));
+        self.queue.push(self.goal, self.key(self.goal));
+    }
+
+    fn distance(&self, position: C) -> WithInfinity<u64> {
+        self.distances.get(&position).copied().unwrap_or_default()
+    }
+
+    fn lookahead(&self, position: C) -> WithInfinity<u64> {
+        self.lookahead.get(&position).copied().unwrap_or_default()
+    }
+
+    fn key(&self, position: C) -> Key {
+        let best = self.distance(position).min(self.lookahead(position));
+        (
+            best + self.heuristic.heuristic_distance(self.start, position)
+                + WithInfinity::Normal(self.key_modifier),
+            best,
+        )
+    }
+
+    fn tile_changed<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, position: C) {
+        // Only the cost of stepping onto the tile has changed, which only the tile and its
+        // neighbors use.
+        self.update(open_positions, position);
+        for neighbor in self.neighborhood.neighbors(position) {
+            self.update(open_positions, neighbor);
+        }
+    }
+
+    /// Recomputes the one step lookahead distance of a position from the distances of its
+    /// neighbors, and queues it if that no longer agrees with its distance. Stepping onto a blocked
+    /// position costs infinity, so one is simply forgotten rather than queued, unless it is the
+    /// start, which the unit can still step off of.
+    fn update<P: Passable<C> + ?Sized>(&mut self, open_positions: &P, position: C) {
+        if position != self.goal {
+            if position != self.start && !open_positions.is_passable(position) {
+                self.distances.remove(&position);
+                self.lookahead.remove(&position);
+                self.queue.remove(&position);
+                return;
+            }
+            let lookahead = self
+                .neighborhood
+                .neighbors(position)
+                .filter(|neighbor| open_positions.is_passable(*neighbor))
+                .map(|neighbor| self.distance(neighbor) + WithInfinity::Normal(1))
+                .min()
+                .unwrap_or_default();
+            self.lookahead.insert(position, lookahead);
+        }
+        self.queue.remove(&position);
+        if self.distance(position) != self.lookahead(position) {
+            self.queue.push(position, self.key(position));
+        }
+    }
+
+    fn compute_shortest_path<P: Passable<C> + ?Sized>(&mut self, open_positions: &P) {
+        while let Some((_, top)) = self.queue.peek_min() {
+            let top = *top;
+            if top >= self.key(self.start)
+                && self.lookahead(self.start) == self.distance(self.start)
+            {
+                break;
+            }
+            self.stats.max_frontier = self.stats.max_frontier.max(self.queue.len());
+            let (position, old_key) = self.queue.pop_min().expect("queue is nonempty");
+            let new_key = self.key(position);
+            if old_key < new_key {
+                self.queue.push(position, new_key);
+                continue;
+            }
+            self.stats.nodes_expanded += 1;
+            if self.distance(position) > self.lookahead(position) {
+                self.distances.insert(position, self.lookahead(position));
+                for neighbor in self.neighborhood.neighbors(position) {
+                    self.update(open_positions, neighbor);
+                }
+            } else {
+                self.distances.insert(position, WithInfinity::Infinity);
+                self.update(open_positions, position);
+                for neighbor in self.neighborhood.neighbors(position) {
+                    self.update(open_positions, neighbor);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn incremental_planner_test() {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        pathfinding::{all_pairs_shortest_paths, HammingDistance},
+        test_util::assert_valid_path,
+    };
+
+    let statically_open: BTreeSet<Position> = (0..12)
+        .flat_map(|x| (0..12).map(move |y| Position::new(x, y, 0)))
+        .filter(|p| !(p.x == 6 && p.y < 10) && !(p.y == 4 && (2..6).contains(&p.x)))
+        .collect();
+    let all_pairs = all_pairs_shortest_paths(&statically_open);
+    let (start, goal) = (Position::new(0, 0, 0), Position::new(11, 0, 0));
+
+    let changes = [
+        Position::new(6, 10, 0),
+        Position::new(7, 11, 0),
+        Position::new(0, 1, 0),
+        Position::new(6, 10, 0),
+        Position::new(9, 0, 0),
+        Position::new(10, 1, 0),
+        Position::new(11, 1, 0),
+        Position::new(7, 11, 0),
+    ];
+
+    fn check<H: Heuristic>(
+        mut planner: IncrementalPlanner<H>,
+        statically_open: &BTreeSet<Position>,
+        changes: &[Position],
+    ) {
+        let mut open_positions = statically_open.clone();
+        let expected =
+            HammingDistance.find_shortest_path(&open_positions, planner.start(), planner.goal());
+        assert_eq!(
+            planner.find_path(&open_positions).map(|path| path.len()),
+            expected.map(|path| path.len())
+        );
+
+        for (step, position) in changes.iter().copied().enumerate() {
+            // The unit walks a step along its current path in between changes to the map.
+            if step % 2 == 1 {
+                if let Some(next) = planner
+                    .find_path(&open_positions)
+                    .and_then(|path| path.front().copied())
+                {
+                    planner.move_to(next);
+                }
+            }
+            if open_positions.remove(&position) {
+                planner.tile_blocked(&open_positions, position);
+            } else {
+                open_positions.insert(position);
+                planner.tile_opened(&open_positions, position);
+            }
+            let expected = HammingDistance.find_shortest_path(
+                &open_positions,
+                planner.start(),
+                planner.goal(),
+            );
+            let found = planner.find_path(&open_positions);
+            assert_eq!(
+                found.as_ref().map(|path| path.len()),
+                expected.map(|path| path.len())
+            );
+            if let Some(path) = found {
+                assert_valid_path(
+                    &VonNeumann,
+                    &open_positions,
+                    planner.start(),
+                    path,
+                    planner.goal(),
+                );
+            }
+        }
+    }
+
+    check(
+        IncrementalPlanner::new(HammingDistance, start, goal),
+        &statically_open,
+        &changes,
+    );
+    // Every change toggles a statically open tile, so the all pairs heuristic stays admissible.
+    check(
+        IncrementalPlanner::new(&all_pairs, start, goal),
+        &statically_open,
+        &changes,
+    );
+    // Closing the gap in the wall cuts the goal off entirely, until it is opened again.
+    let mut open_positions = statically_open.clone();
+    let mut planner = IncrementalPlanner::new(&all_pairs, start, goal);
+    let length = planner.find_path(&open_positions).map(|path| path.len());
+    let gap = [Position::new(6, 10, 0), Position::new(6, 11, 0)];
+    for position in gap {
+        open_positions.remove(&position);
+        planner.tile_blocked(&open_positions, position);
+    }
+    assert_eq!(planner.find_path(&open_positions), None);
+    for position in gap {
+        open_positions.insert(position);
+        planner.tile_opened(&open_positions, position);
+    }
+    assert_eq!(
+        planner.find_path(&open_positions).map(|path| path.len()),
+        length
+    );
+
+    // In an open room, blocking a tile beside the path needs no repairs at all, while blocking
+    // one on it repairs less than searching again from scratch would, as the wall itself is never
+    // expanded.
+    let mut open_positions: BTreeSet<Position> = (0..12)
+        .flat_map(|x| (0..12).map(move |y| Position::new(x, y, 0)))
+        .collect();
+    let mut planner = IncrementalPlanner::new(HammingDistance, start, goal);
+    planner.find_path(&open_positions);
+    for (position, repairs) in [
+        (Position::new(1, 1, 0), Some(0)),
+        (Position::new(1, 0, 0), None),
+    ] {
+        let before = planner.stats().nodes_expanded;
+        open_positions.remove(&position);
+        planner.tile_blocked(&open_positions, position);
+        planner.find_path(&open_positions).unwrap();
+        let expanded = planner.stats().nodes_expanded - before;
+        let mut fresh = IncrementalPlanner::new(HammingDistance, start, goal);
+        fresh.find_path(&open_positions);
+        match repairs {
+            Some(repairs) => assert_eq!(expanded, repairs),
+            None => assert!(expanded < fresh.stats().nodes_expanded),
+        }
+    }
+}
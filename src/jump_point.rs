@@ -0,0 +1,310 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    ops::{Add, Mul, Sub},
+};
+
+use crate::{
+    pathfinding::{PathError, PathResult, SearchStats},
+    Coordinate, Passable, Position, Position2,
+};
+
+/// A coordinate on a square grid, whose adjacent positions are a single step along one of its
+/// axes in either direction.
+pub trait GridCoordinate:
+    Coordinate + Add<Output = Self> + Sub<Output = Self> + Mul<i64, Output = Self>
+{
+    /// A single step in the positive direction along each axis.
+    const AXES: &'static [Self];
+}
+
+impl GridCoordinate for Position {
+    const AXES: &'static [Self] = &[
+        Position { x: 1, y: 0, z: 0 },
+        Position { x: 0, y: 1, z: 0 },
+        Position { x: 0, y: 0, z: 1 },
+    ];
+}
+
+impl GridCoordinate for Position2 {
+    const AXES: &'static [Self] = &[Position2 { x: 1, y: 0 }, Position2 { x: 0, y: 1 }];
+}
+
+/// A direction of movement, as an axis and whether it is travelled along backwards.
+type Direction = (usize, bool);
+
+fn step<C: GridCoordinate>((axis, backwards): Direction) -> C {
+    C::AXES[axis] * if backwards { -1 } else { 1 }
+}
+
+/// # Jump Point Search
+///
+/// Finds the shortest path from `start` to `end`, moving between adjacent positions as in
+/// [`Heuristic::find_shortest_path`](crate::pathfinding::Heuristic::find_shortest_path), and
+/// returns a path of the same length as it would. Since every step costs the same, there are
+/// usually many equally short paths which differ only in the order of their steps, and A* expands
+/// the positions along all of them. Jump point search only considers paths which move along the
+/// axes in order, switching to a later axis whenever they like but only switching back to an
+/// earlier one when an obstacle forces them to, and skips straight past every position on such a
+/// path until it reaches one where a decision has to be made.
+///
+/// Far fewer positions are expanded, but deciding where each jump stops means scanning ahead
+/// along the grid, and a jump along one axis scans along every later axis from each position it
+/// passes, so a search may look at most of the open positions between the start and the end
+/// however few it expands. In the benchmarks this pays off on maps broken up by obstacles, where
+/// A* is led into dead ends and jump point search is several times faster. Across open fields,
+/// where the Hamming distance is already exact and A* heads straight for the end, A* is faster.
+///
+/// Jumps continue until they hit a blocked position, so only paths at most `max_detour` steps
+/// longer than the distance from `start` to `end` are considered, and positions which only lie on
+/// longer paths are treated as blocked. This keeps the search finite even when the map is not, such
+/// as one given by a closure. Over a finite map, passing [`u64::MAX`] considers every path.
+pub fn jump_point_search<C: GridCoordinate, P: Passable<C> + ?Sized>(
+    open_positions: &P,
+    start: C,
+    end: C,
+    max_detour: u64,
+) -> Option<VecDeque<C>> {
+    try_jump_point_search(open_positions, start, end, max_detour)
+        .ok()
+        .map(|result| result.path)
+}
+
+/// Finds the shortest path from `start` to `end` as in [`jump_point_search`], reporting the work
+/// done by the search, or why it failed. Only the jump points are counted as expanded.
+pub fn try_jump_point_search<C: GridCoordinate, P: Passable<C> + ?Sized>(
+    open_positions: &P,
+    start: C,
+    end: C,
+    max_detour: u64,
+) -> Result<PathResult<C>, PathError> {
+    if !open_positions.is_passable(end) {
+        return Err(PathError::EndBlocked);
+    }
+    let max_length = start.distance(end).saturating_add(max_detour);
+    let open_positions = |position: C| {
+        start
+            .distance(position)
+            .saturating_add(position.distance(end))
+            <= max_length
+            && open_positions.is_passable(position)
+    };
+    let open_positions = &open_positions;
+
+    type State<C> = (C, Option<Direction>);
+    let mut queue: BinaryHeap<Reverse<(u64, u64, State<C>)>> = BinaryHeap::new();
+    let mut distances: HashMap<State<C>, u64> = HashMap::new();
+    let mut predecessor: HashMap<State<C>, State<C>> = HashMap::new();
+    let mut stats = SearchStats::default();
+    distances.insert((start, None), 0);
+    queue.push(Reverse((start.distance(end), 0, (start, None))));
+
+    while let Some(Reverse((_estimate, distance, state))) = queue.pop() {
+        stats.max_frontier = stats.max_frontier.max(queue.len() + 1);
+        if distances.get(&state).is_some_and(|best| *best < distance) {
+            continue;
+        }
+        let (position, direction) = state;
+        if position == end {
+            let mut path = VecDeque::new();
+            let mut current = state;
+            while let Some(previous) = predecessor.get(&current) {
+                let (to, direction) = current;
+                let step = step::<C>(direction.expect("only the start has no direction"));
+                let mut cursor = to;
+                while cursor != previous.0 {
+                    path.push_front(cursor);
+                    cursor = cursor - step;
+                }
+                current = *previous;
+            }
+            return Ok(PathResult {
+                path,
+                cost: distance,
+                stats,
+            });
+        }
+
+        stats.nodes_expanded += 1;
+        for direction in successors(open_positions, position, direction) {
+            if let Some(jump_point) = jump(open_positions, position, direction, end) {
+                let next = (jump_point, Some(direction));
+                let alt = distance + position.distance(jump_point);
+                if distances.get(&next).is_none_or(|best| alt < *best) {
+                    distances.insert(next, alt);
+                    predecessor.insert(next, state);
+                    queue.push(Reverse((alt + jump_point.distance(end), alt, next)));
+                }
+            }
+        }
+    }
+
+    Err(PathError::Exhausted(stats))
+}
+
+/// The directions worth searching in after arriving at `position` by moving in `direction`.
+/// Later axes are always worth trying, while earlier ones are only worth trying when they are
+/// forced, that is when the position beside the one we came from is blocked.
+fn successors<C: GridCoordinate, P: Passable<C> + ?Sized>(
+    open_positions: &P,
+    position: C,
+    direction: Option<Direction>,
+) -> Vec<Direction> {
+    let all = (0..C::AXES.len()).flat_map(|axis| [(axis, false), (axis, true)]);
+    match direction {
+        None => all.collect(),
+        Some(direction) => {
+            let parent = position - step(direction);
+            all.filter(|turn| {
+                turn.0 > direction.0
+                    || *turn == direction
+                    || (turn.0 < direction.0 && is_forced(open_positions, position, parent, *turn))
+            })
+            .collect()
+        }
+    }
+}
+
+fn is_forced<C: GridCoordinate, P: Passable<C> + ?Sized>(
+    open_positions: &P,
+    position: C,
+    parent: C,
+    turn: Direction,
+) -> bool {
+    !open_positions.is_passable(parent + step(turn))
+        && open_positions.is_passable(position + step(turn))
+}
+
+/// Moves from `position` in `direction` until reaching a jump point, which is either the end, a
+/// position with a forced turn, or a position from which a jump along a later axis finds a jump
+/// point of its own. Returns [`None`] if a blocked position is hit first.
+///
+/// Whether the positions to the side along each earlier axis are open is carried from one step to
+/// the next, so that checking for forced turns only looks up each of those positions once.
+fn jump<C: GridCoordinate, P: Passable<C> + ?Sized>(
+    open_positions: &P,
+    position: C,
+    direction: Direction,
+    end: C,
+) -> Option<C> {
+    let step = step::<C>(direction);
+    let mut current = position + step;
+    if !open_positions.is_passable(current) {
+        return None;
+    }
+    // There are at most two earlier axes, each with a side in either direction.
+    let sides = 2 * direction.0;
+    let side = |index: usize| self::step::<C>((index / 2, index % 2 == 1));
+    let mut side_open = [false; 4];
+    for (index, open) in side_open.iter_mut().enumerate().take(sides) {
+        *open = open_positions.is_passable(position + side(index));
+    }
+    loop {
+        if current == end {
+            return Some(current);
+        }
+        let mut forced = false;
+        for (index, was_open) in side_open.iter_mut().enumerate().take(sides) {
+            let open = open_positions.is_passable(current + side(index));
+            forced |= open && !*was_open;
+            *was_open = open;
+        }
+        if forced {
+            return Some(current);
+        }
+        for axis in direction.0 + 1..C::AXES.len() {
+            for backwards in [false, true] {
+                if jump(open_positions, current, (axis, backwards), end).is_some() {
+                    return Some(current);
+                }
+            }
+        }
+        current = current + step;
+        if !open_positions.is_passable(current) {
+            return None;
+        }
+    }
+}
+
+#[test]
+fn jump_point_search_test() {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        neighborhood::VonNeumann,
+        pathfinding::{HammingDistance, Heuristic},
+        test_util::assert_valid_path,
+    };
+
+    // A deterministic scattering of obstacles, dense enough to leave some positions unreachable.
+    let blocked = |seed: i64| (seed.wrapping_mul(2654435761) >> 7) % 10 < 3;
+
+    let open_positions: BTreeSet<Position2> = (0..12)
+        .flat_map(|x| (0..12).map(move |y| Position2::new(x, y)))
+        .filter(|position| !blocked(position.x * 31 + position.y * 17))
+        .collect();
+    let open_positions3: BTreeSet<Position> = (0..6)
+        .flat_map(|x| (0..6).flat_map(move |y| (0..4).map(move |z| Position::new(x, y, z))))
+        .filter(|position| !blocked(position.x * 31 + position.y * 17 + position.z * 7))
+        .collect();
+
+    fn check<C: GridCoordinate>(open_positions: &BTreeSet<C>, heuristic: &impl Heuristic<C>) {
+        let mut reachable = 0;
+        for start in open_positions.iter().copied().step_by(13) {
+            for end in open_positions.iter().copied() {
+                let expected = heuristic.find_shortest_path(open_positions, start, end);
+                let found = jump_point_search(open_positions, start, end, u64::MAX);
+                assert_eq!(
+                    found.as_ref().map(|path| path.len()),
+                    expected.map(|path| path.len())
+                );
+                if let Some(path) = found {
+                    reachable += 1;
+                    assert_valid_path(&VonNeumann, open_positions, start, path, end);
+                }
+            }
+        }
+        assert!(reachable > 0);
+    }
+    check(&open_positions, &HammingDistance);
+    check(&open_positions3, &HammingDistance);
+
+    // Across an open field, only the positions where the path turns are expanded.
+    let open_field: BTreeSet<Position2> = (0..12)
+        .flat_map(|x| (0..12).map(move |y| Position2::new(x, y)))
+        .collect();
+    let (start, end) = (Position2::new(0, 0), Position2::new(11, 11));
+    let jumped = try_jump_point_search(&open_field, start, end, u64::MAX).unwrap();
+    let expected = HammingDistance
+        .try_find_shortest_path(&open_field, start, end)
+        .unwrap();
+    assert_eq!(jumped.cost, expected.cost);
+    assert!(jumped.stats.nodes_expanded < expected.stats.nodes_expanded);
+
+    assert_eq!(
+        jump_point_search(
+            &open_positions,
+            Position2::new(-1, 0),
+            Position2::new(-5, 0),
+            u64::MAX
+        ),
+        None
+    );
+
+    // An endless plain with a wall to go around, and a position walled in on every side.
+    let plain = |position: Position2| position.x != 5 || position.y > 3;
+    let (start, end) = (Position2::new(0, 0), Position2::new(10, 0));
+    assert_eq!(
+        jump_point_search(&plain, start, end, 8).map(|path| path.len()),
+        Some(18)
+    );
+    assert!(matches!(
+        try_jump_point_search(&plain, start, end, 7),
+        Err(PathError::Exhausted(_))
+    ));
+    let walled_in = |position: Position2| Position2::new(20, 0).distance(position) != 1;
+    assert!(matches!(
+        try_jump_point_search(&walled_in, start, Position2::new(20, 0), 100),
+        Err(PathError::Exhausted(_))
+    ));
+}
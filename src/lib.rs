@@ -1,5 +1,3 @@
-#![feature(map_first_last)]
-
 //! # Positioning
 //!
 //! This is a library to encapsulate some code I've found myself repeating recently around game
@@ -9,10 +7,60 @@
 /// Contains an implementation of A* and all-pairs-shortest paths with fun a twist where the latter
 /// performs well as a heuristic in a specific context.
 pub mod pathfinding;
+
+mod chunk;
+mod coordinate;
+mod grid;
+mod passable;
 mod position;
+mod position2;
+#[cfg(test)]
+mod test_util;
+
+/// Contains coordinates for hexagonal grids, which can be searched just like square ones.
+pub mod hex;
+
+/// Contains the rules for which positions may be moved between in a single step, such as whether
+/// or not diagonal movement is allowed.
+pub mod neighborhood;
 
 /// Contains an implementation of an iterator which returns positions out from a given start in
 /// breadth first order.
 pub mod bfs;
 
+/// Contains hierarchical pathfinding, which scales to maps far too large for the all-pairs
+/// heuristic by searching between clusters of the map before searching within them.
+pub mod hierarchical;
+
+/// Contains jump point search, an alternative to A* for finding shortest paths over grids broken up
+/// by obstacles, where every step costs the same.
+pub mod jump_point;
+
+/// Contains a planner which repairs its previous search when tiles are blocked or opened, rather
+/// than searching again from scratch.
+pub mod incremental;
+
+/// Contains an A* search which can be paused and resumed, for spreading long searches over several
+/// frames.
+pub mod resumable;
+
+/// Contains searches for the nearest of many goals, such as the nearest tree or stockpile.
+pub mod nearest;
+
+/// Contains Dijkstra maps, fields of distances from many sources at once which units can follow
+/// towards or away from the nearest of them.
+pub mod dijkstra_map;
+
+/// Contains flow fields, which lead any number of units to a shared goal from anywhere on the map.
+pub mod flow_field;
+
+/// Contains planning for many units moving at once, which must avoid running into each other as
+/// well as into the map.
+pub mod multi_agent;
+
+pub use chunk::{Chunk, ChunkMap, CHUNK_SIZE};
+pub use coordinate::{Coordinate, Node};
+pub use grid::Grid3;
+pub use passable::{MovementCost, Passable, PassableSet, UnitCost};
 pub use position::Position;
+pub use position2::Position2;
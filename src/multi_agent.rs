@@ -0,0 +1,626 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque},
+};
+
+use crate::{
+    neighborhood::{Neighborhood, VonNeumann},
+    pathfinding::{HammingDistance, Heuristic, WithInfinity},
+    Coordinate, Node, Passable, Position,
+};
+
+/// # Reservation Table
+///
+/// Records where other units will be at each tick, so that paths can be planned around them. A
+/// unit moving onto a position at a tick it is reserved for is a vertex conflict, and a unit moving
+/// along an edge which another unit moves along in the opposite direction at the same tick, so
+/// that the two would swap places, is an edge conflict. Units which have finished their paths are
+/// parked at their last position, which stays reserved from then on.
+#[derive(Debug, Clone)]
+pub struct ReservationTable<C: Node = Position> {
+    vertices: HashSet<(C, u64)>,
+    edges: HashSet<(C, C, u64)>,
+    latest: HashMap<C, u64>,
+    parked: HashMap<C, u64>,
+    horizon: u64,
+}
+
+impl<C: Node> Default for ReservationTable<C> {
+    fn default() -> Self {
+        ReservationTable {
+            vertices: HashSet::new(),
+            edges: HashSet::new(),
+            latest: HashMap::new(),
+            parked: HashMap::new(),
+            horizon: 0,
+        }
+    }
+}
+
+impl<C: Node> ReservationTable<C> {
+    /// Creates an empty [`ReservationTable`].
+    pub fn new() -> Self {
+        ReservationTable::default()
+    }
+
+    /// Reserves a position at a single tick.
+    pub fn reserve_vertex(&mut self, position: C, tick: u64) {
+        self.vertices.insert((position, tick));
+        let latest = self.latest.entry(position).or_insert(tick);
+        *latest = (*latest).max(tick);
+        self.horizon = self.horizon.max(tick);
+    }
+
+    /// Reserves the move from `from` to `to` which starts at the given tick and ends at the next.
+    pub fn reserve_edge(&mut self, from: C, to: C, tick: u64) {
+        self.edges.insert((from, to, tick));
+        self.horizon = self.horizon.max(tick + 1);
+    }
+
+    /// Reserves a position at every tick from the given one onwards.
+    pub fn park(&mut self, position: C, tick: u64) {
+        let parked = self.parked.entry(position).or_insert(tick);
+        *parked = (*parked).min(tick);
+        self.horizon = self.horizon.max(tick);
+    }
+
+    /// Reserves every position and move along a timed path, as returned by [`find_timed_path`],
+    /// which leaves `start` at `start_tick`. The unit is parked at the end of the path once it
+    /// arrives.
+    pub fn reserve_path(&mut self, start: C, start_tick: u64, path: impl IntoIterator<Item = C>) {
+        self.reserve_vertex(start, start_tick);
+        let (mut previous, mut tick) = (start, start_tick);
+        for position in path {
+            self.reserve_edge(previous, position, tick);
+            tick += 1;
+            self.reserve_vertex(position, tick);
+            previous = position;
+        }
+        self.park(previous, tick);
+    }
+
+    /// Removes every reservation.
+    pub fn clear(&mut self) {
+        *self = ReservationTable::default();
+    }
+
+    /// Returns whether or not the position is reserved at the given tick.
+    pub fn is_reserved(&self, position: C, tick: u64) -> bool {
+        self.vertices.contains(&(position, tick))
+            || self
+                .parked
+                .get(&position)
+                .is_some_and(|parked| *parked <= tick)
+    }
+
+    /// Returns whether or not a unit may move from `from` to `to`, or wait where it is if they are
+    /// the same, starting at the given tick without conflicting with any reservation.
+    pub fn is_move_allowed(&self, from: C, to: C, tick: u64) -> bool {
+        !self.is_reserved(to, tick + 1) && !self.edges.contains(&(to, from, tick))
+    }
+
+    /// Returns whether or not a unit may stay at the given position forever from the given tick.
+    pub fn is_free_from(&self, position: C, tick: u64) -> bool {
+        !self.parked.contains_key(&position)
+            && self
+                .latest
+                .get(&position)
+                .is_none_or(|latest| *latest < tick)
+    }
+
+    /// The last tick at which anything changes in the table. After it, every position is either
+    /// parked on or free forever.
+    pub fn horizon(&self) -> u64 {
+        self.horizon
+    }
+}
+
+/// Finds the quickest path from `start` to `end` which avoids every reservation, leaving `start`
+/// at `start_tick`. Each step of the returned path is the position at the following tick, so
+/// waiting in place shows up as the same position repeated. As with
+/// [`Heuristic::find_shortest_path`], the path excludes `start` and includes `end`, and the unit is
+/// only considered to have arrived once it can stay at `end` forever without conflicts.
+///
+/// The heuristic is used as a lower bound on the number of moves to `end`, ignoring time.
+pub fn find_timed_path<H: Heuristic<C>, C: Coordinate, P: Passable<C> + ?Sized>(
+    heuristic: &H,
+    open_positions: &P,
+    reservations: &ReservationTable<C>,
+    start: C,
+    end: C,
+    start_tick: u64,
+) -> Option<VecDeque<C>> {
+    find_timed_path_with(
+        heuristic,
+        open_positions,
+        reservations,
+        start,
+        end,
+        start_tick,
+        &VonNeumann,
+    )
+}
+
+/// Finds the quickest path from `start` to `end` as in [`find_timed_path`], moving between
+/// positions according to the given [`Neighborhood`].
+pub fn find_timed_path_with<
+    H: Heuristic<C>,
+    C: Node,
+    P: Passable<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    heuristic: &H,
+    open_positions: &P,
+    reservations: &ReservationTable<C>,
+    start: C,
+    end: C,
+    start_tick: u64,
+    neighborhood: &N,
+) -> Option<VecDeque<C>> {
+    if !open_positions.is_passable(end) {
+        return None;
+    }
+    let estimate = match heuristic.heuristic_distance(start, end) {
+        WithInfinity::Normal(estimate) => estimate,
+        WithInfinity::Infinity => return None,
+    };
+
+    // Once every reservation has passed, waiting any longer can't help, so all later ticks are
+    // treated as the same one. This keeps the search finite on a finite map.
+    let settled = reservations.horizon().max(start_tick) + 1;
+    let mut earliest: HashMap<(C, u64), u64> = HashMap::new();
+    let mut predecessor: HashMap<(C, u64), (C, u64)> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    earliest.insert((start, start_tick.min(settled)), start_tick);
+    queue.push(Reverse((start_tick + estimate, start_tick, start)));
+
+    while let Some(Reverse((_, tick, position))) = queue.pop() {
+        if earliest.get(&(position, tick.min(settled))) != Some(&tick) {
+            continue;
+        }
+        if position == end && reservations.is_free_from(end, tick) {
+            let mut path = VecDeque::new();
+            let mut current = (position, tick);
+            while current != (start, start_tick) {
+                path.push_front(current.0);
+                current = predecessor[&current];
+            }
+            return Some(path);
+        }
+
+        let moves = neighborhood
+            .neighbors(position)
+            .filter(|neighbor| open_positions.is_passable(*neighbor))
+            .chain(std::iter::once(position));
+        for next in moves {
+            if !reservations.is_move_allowed(position, next, tick) {
+                continue;
+            }
+            let estimate = match heuristic.heuristic_distance(next, end) {
+                WithInfinity::Normal(estimate) => estimate,
+                WithInfinity::Infinity => continue,
+            };
+            let key = (next, (tick + 1).min(settled));
+            if earliest
+                .get(&key)
+                .is_some_and(|earliest| *earliest <= tick + 1)
+            {
+                continue;
+            }
+            earliest.insert(key, tick + 1);
+            predecessor.insert((next, tick + 1), (position, tick));
+            queue.push(Reverse((tick + 1 + estimate, tick + 1, next)));
+        }
+    }
+
+    None
+}
+
+#[test]
+fn space_time_test() {
+    use std::collections::BTreeSet;
+
+    use itertools::Itertools;
+
+    use crate::{pathfinding::HammingDistance, test_util::assert_valid_path, Position2};
+
+    // A corridor with a single bay in the middle where units can step aside.
+    let open_positions: BTreeSet<Position2> = (0..9)
+        .map(|x| Position2::new(x, 0))
+        .chain(std::iter::once(Position2::new(6, 1)))
+        .collect();
+    let (left, right) = (Position2::new(0, 0), Position2::new(8, 0));
+
+    let mut reservations = ReservationTable::new();
+    let first = find_timed_path(
+        &HammingDistance,
+        &open_positions,
+        &reservations,
+        left,
+        right,
+        0,
+    )
+    .unwrap();
+    assert_eq!(first.len(), 8);
+    reservations.reserve_path(left, 0, first.iter().copied());
+
+    let second = find_timed_path(
+        &HammingDistance,
+        &open_positions,
+        &reservations,
+        right,
+        left,
+        0,
+    )
+    .unwrap();
+    assert!(second.contains(&Position2::new(6, 1)));
+    assert!(second.len() > 8);
+    // Apart from waiting in place, each unit only ever steps between open neighbors.
+    for (start, path, end) in [(left, &first, right), (right, &second, left)] {
+        let moves = std::iter::once(start).chain(path.iter().copied()).dedup();
+        assert_valid_path(&VonNeumann, &open_positions, start, moves.skip(1), end);
+    }
+
+    // Neither unit is ever in the same place, nor swaps places with the other.
+    let at = |start: Position2, path: &VecDeque<Position2>, tick: usize| {
+        if tick == 0 {
+            start
+        } else {
+            path.get(tick - 1).copied().unwrap_or(*path.back().unwrap())
+        }
+    };
+    for tick in 0..20 {
+        assert_ne!(at(left, &first, tick), at(right, &second, tick));
+        assert!(
+            at(left, &first, tick) != at(right, &second, tick + 1)
+                || at(left, &first, tick + 1) != at(right, &second, tick)
+        );
+    }
+
+    // A unit parked on the goal blocks it forever, which the search notices and gives up on.
+    reservations.reserve_path(right, 0, second);
+    assert_eq!(
+        find_timed_path(
+            &HammingDistance,
+            &open_positions,
+            &reservations,
+            Position2::new(6, 1),
+            left,
+            3
+        ),
+        None
+    );
+    assert!(!reservations.is_move_allowed(Position2::new(1, 0), left, 30));
+
+    // Waiting until a reservation passes is allowed.
+    let mut crossing = ReservationTable::new();
+    crossing.reserve_vertex(Position2::new(1, 0), 1);
+    let path = find_timed_path(
+        &HammingDistance,
+        &open_positions,
+        &crossing,
+        left,
+        Position2::new(2, 0),
+        0,
+    )
+    .unwrap();
+    assert_eq!(
+        path,
+        VecDeque::from(vec![left, Position2::new(1, 0), Position2::new(2, 0)])
+    );
+}
+
+/// A restriction placed on a single agent by [`ConflictBasedSearch`] to resolve a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constraint<C> {
+    /// The agent may not be at `position` at `tick`.
+    Vertex { position: C, tick: u64 },
+    /// The agent may not move from `from` to `to` starting at `tick`.
+    Edge { from: C, to: C, tick: u64 },
+}
+
+/// A node of the constraint tree searched by [`ConflictBasedSearch`].
+struct ConstraintNode<C> {
+    constraints: Vec<(usize, Constraint<C>)>,
+    paths: Vec<VecDeque<C>>,
+    conflicts: usize,
+}
+
+/// Returns where an agent following `path` from `start` is at the given tick, staying at the end
+/// of the path once it has arrived.
+fn position_at<C: Node>(start: C, path: &VecDeque<C>, tick: u64) -> C {
+    match tick.checked_sub(1) {
+        None => start,
+        Some(index) => path
+            .get(index as usize)
+            .or(path.back())
+            .copied()
+            .unwrap_or(start),
+    }
+}
+
+/// Returns the first conflict between two agents following the given paths, as the pair of
+/// constraints which would each resolve it for one of the two agents.
+fn first_conflict<C: Node>(
+    (start, path): (C, &VecDeque<C>),
+    (other_start, other_path): (C, &VecDeque<C>),
+) -> Option<(Constraint<C>, Constraint<C>)> {
+    let horizon = path.len().max(other_path.len()) as u64;
+    for tick in 0..=horizon {
+        let (position, other) = (
+            position_at(start, path, tick),
+            position_at(other_start, other_path, tick),
+        );
+        if position == other {
+            let constraint = Constraint::Vertex { position, tick };
+            return Some((constraint, constraint));
+        }
+        let (next, other_next) = (
+            position_at(start, path, tick + 1),
+            position_at(other_start, other_path, tick + 1),
+        );
+        if next == other && other_next == position {
+            return Some((
+                Constraint::Edge {
+                    from: position,
+                    to: next,
+                    tick,
+                },
+                Constraint::Edge {
+                    from: other,
+                    to: other_next,
+                    tick,
+                },
+            ));
+        }
+    }
+    None
+}
+
+/// # Conflict Based Search
+///
+/// Plans collision free paths for a group of agents at once, each moving from its start to its
+/// goal, where agents may neither be at the same position at the same tick nor swap places. Each
+/// agent is first planned for on its own, and whenever two of the resulting paths conflict, the
+/// search branches on which of the two agents has to avoid the conflict, replanning that agent with
+/// [`find_timed_path`] around a growing set of constraints. The branches are explored cheapest
+/// first, where the cost of a plan is the sum of the number of ticks each agent takes to arrive, so
+/// the first plan found without any conflicts has the lowest possible cost.
+///
+/// Setting `suboptimality` above 1 trades that guarantee for speed in the style of ECBS: rather than
+/// the cheapest branch, the search explores the branch with the fewest conflicts among those costing
+/// at most `suboptimality` times the cheapest, and the plan found costs at most that many times the
+/// optimal one. The agents themselves are still planned for optimally.
+pub struct ConflictBasedSearch<H = HammingDistance> {
+    /// The heuristic used when planning each agent's path.
+    pub heuristic: H,
+    /// The number of branches of the search to explore before giving up.
+    pub max_expansions: usize,
+    /// How much more than the optimal cost a plan may cost, as a factor of at least 1.
+    pub suboptimality: f64,
+}
+
+impl Default for ConflictBasedSearch {
+    fn default() -> Self {
+        ConflictBasedSearch {
+            heuristic: HammingDistance,
+            max_expansions: 10_000,
+            suboptimality: 1.,
+        }
+    }
+}
+
+impl<H> ConflictBasedSearch<H> {
+    /// Plans paths for each of the `(start, goal)` pairs in `agents`, all leaving their starts at
+    /// tick 0. Each path excludes its start and includes its goal, and each step of it is the
+    /// agent's position at the following tick, with agents waiting at their goals once they
+    /// arrive. Returns [`None`] if there is no plan, or if none was found within `max_expansions`.
+    pub fn solve<C: Coordinate, P: Passable<C> + ?Sized>(
+        &self,
+        open_positions: &P,
+        agents: &[(C, C)],
+    ) -> Option<Vec<VecDeque<C>>>
+    where
+        H: Heuristic<C>,
+    {
+        self.solve_with(open_positions, agents, &VonNeumann)
+    }
+
+    /// Plans paths for each of the `(start, goal)` pairs in `agents` as in
+    /// [`ConflictBasedSearch::solve`], moving between positions according to the given
+    /// [`Neighborhood`].
+    pub fn solve_with<C: Node, P: Passable<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        &self,
+        open_positions: &P,
+        agents: &[(C, C)],
+        neighborhood: &N,
+    ) -> Option<Vec<VecDeque<C>>>
+    where
+        H: Heuristic<C>,
+    {
+        let starts: HashSet<C> = agents.iter().map(|(start, _)| *start).collect();
+        let goals: HashSet<C> = agents.iter().map(|(_, goal)| *goal).collect();
+        if starts.len() != agents.len() || goals.len() != agents.len() {
+            return None;
+        }
+
+        let plan = |agent: usize, constraints: &[(usize, Constraint<C>)]| {
+            let mut reservations = ReservationTable::new();
+            let constraints = constraints
+                .iter()
+                .filter(|(constrained, _)| *constrained == agent);
+            for (_, constraint) in constraints {
+                match *constraint {
+                    Constraint::Vertex { position, tick } => {
+                        reservations.reserve_vertex(position, tick);
+                    }
+                    // The table forbids moves which would swap places with a reserved one.
+                    Constraint::Edge { from, to, tick } => {
+                        reservations.reserve_edge(to, from, tick)
+                    }
+                }
+            }
+            let (start, goal) = agents[agent];
+            find_timed_path_with(
+                &self.heuristic,
+                open_positions,
+                &reservations,
+                start,
+                goal,
+                0,
+                neighborhood,
+            )
+        };
+        let conflicts = |paths: &[VecDeque<C>]| {
+            let mut count = 0;
+            let mut first = None;
+            for a in 0..agents.len() {
+                for b in a + 1..agents.len() {
+                    if let Some(conflict) =
+                        first_conflict((agents[a].0, &paths[a]), (agents[b].0, &paths[b]))
+                    {
+                        count += 1;
+                        first.get_or_insert((a, b, conflict));
+                    }
+                }
+            }
+            (count, first)
+        };
+
+        let paths = (0..agents.len())
+            .map(|agent| plan(agent, &[]))
+            .collect::<Option<Vec<_>>>()?;
+        let mut open: BTreeMap<(u64, usize), ConstraintNode<C>> = BTreeMap::new();
+        let mut next_id = 0;
+        open.insert(
+            (paths.iter().map(|path| path.len() as u64).sum(), next_id),
+            ConstraintNode {
+                constraints: Vec::new(),
+                conflicts: conflicts(&paths).0,
+                paths,
+            },
+        );
+
+        for _ in 0..self.max_expansions {
+            let (lowest, _) = *open.keys().next()?;
+            let bound = ((lowest as f64 * self.suboptimality) as u64).max(lowest);
+            let key = *open
+                .range(..=(bound, usize::MAX))
+                .min_by_key(|(key, node)| (node.conflicts, **key))?
+                .0;
+            let node = open.remove(&key).expect("key was just found");
+            let (a, b, (constraint_a, constraint_b)) = match conflicts(&node.paths).1 {
+                None => return Some(node.paths),
+                Some(conflict) => conflict,
+            };
+
+            for (agent, constraint) in [(a, constraint_a), (b, constraint_b)] {
+                let mut constraints = node.constraints.clone();
+                constraints.push((agent, constraint));
+                if let Some(path) = plan(agent, &constraints) {
+                    let mut paths = node.paths.clone();
+                    paths[agent] = path;
+                    let cost = paths.iter().map(|path| path.len() as u64).sum();
+                    next_id += 1;
+                    open.insert(
+                        (cost, next_id),
+                        ConstraintNode {
+                            constraints,
+                            conflicts: conflicts(&paths).0,
+                            paths,
+                        },
+                    );
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[test]
+fn conflict_based_search_test() {
+    use std::collections::BTreeSet;
+
+    use crate::Position2;
+
+    let collides = |agents: &[(Position2, Position2)], paths: &[VecDeque<Position2>]| {
+        let horizon = paths.iter().map(|path| path.len()).max().unwrap_or(0) as u64;
+        (0..=horizon).any(|tick| {
+            (0..agents.len()).any(|a| {
+                (0..agents.len()).filter(|b| *b != a).any(|b| {
+                    let here =
+                        |agent: usize, tick| position_at(agents[agent].0, &paths[agent], tick);
+                    here(a, tick) == here(b, tick)
+                        || (here(a, tick) == here(b, tick + 1)
+                            && here(a, tick + 1) == here(b, tick))
+                })
+            })
+        })
+    };
+
+    // Two agents swapping ends of a corridor, with a single bay to step aside into.
+    let corridor: BTreeSet<Position2> = (0..6)
+        .map(|x| Position2::new(x, 0))
+        .chain(std::iter::once(Position2::new(2, 1)))
+        .collect();
+    let agents = [
+        (Position2::new(0, 0), Position2::new(5, 0)),
+        (Position2::new(5, 0), Position2::new(0, 0)),
+    ];
+    let optimal = ConflictBasedSearch::default()
+        .solve(&corridor, &agents)
+        .unwrap();
+    assert!(!collides(&agents, &optimal));
+    for ((_, goal), path) in agents.iter().zip(optimal.iter()) {
+        assert_eq!(path.back(), Some(goal));
+    }
+    // The only cost on top of both agents walking straight there is one stepping into the bay and
+    // back out again.
+    assert_eq!(optimal.iter().map(|path| path.len()).sum::<usize>(), 12);
+
+    // Four agents crossing an open room, with a bounded suboptimal search.
+    let room: BTreeSet<Position2> = (0..5)
+        .flat_map(|x| (0..5).map(move |y| Position2::new(x, y)))
+        .collect();
+    let agents = [
+        (Position2::new(0, 2), Position2::new(4, 2)),
+        (Position2::new(4, 2), Position2::new(0, 2)),
+        (Position2::new(2, 0), Position2::new(2, 4)),
+        (Position2::new(2, 4), Position2::new(2, 0)),
+    ];
+    let optimal = ConflictBasedSearch::default()
+        .solve(&room, &agents)
+        .unwrap();
+    let suboptimal = ConflictBasedSearch {
+        suboptimality: 1.5,
+        ..Default::default()
+    }
+    .solve(&room, &agents)
+    .unwrap();
+    let cost = |paths: &[VecDeque<Position2>]| paths.iter().map(|path| path.len()).sum::<usize>();
+    assert!(!collides(&agents, &optimal));
+    assert!(!collides(&agents, &suboptimal));
+    assert!(cost(&optimal) >= 16);
+    assert!(cost(&optimal) <= cost(&suboptimal));
+    assert!(cost(&suboptimal) as f64 <= 1.5 * cost(&optimal) as f64);
+
+    // Without the bay there is no way past each other, and the search gives up.
+    let narrow: BTreeSet<Position2> = (0..4).map(|x| Position2::new(x, 0)).collect();
+    let swap = [
+        (Position2::new(0, 0), Position2::new(3, 0)),
+        (Position2::new(3, 0), Position2::new(0, 0)),
+    ];
+    let limited = ConflictBasedSearch {
+        max_expansions: 50,
+        ..Default::default()
+    };
+    assert_eq!(limited.solve(&narrow, &swap), None);
+    assert_eq!(
+        limited.solve(
+            &narrow,
+            &[swap[0], (Position2::new(0, 0), Position2::new(1, 0))]
+        ),
+        None
+    );
+}
@@ -0,0 +1,222 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    neighborhood::{Neighborhood, VonNeumann},
+    pathfinding::{AStar, Heuristic, WithInfinity},
+    Coordinate, MovementCost, Node, Passable, UnitCost,
+};
+
+/// # Nearest Goal
+///
+/// Finds the shortest path from `start` to whichever of the `goals` is closest, returning that
+/// goal along with the path to it, which as usual excludes the start and includes the goal.
+///
+/// The search is guided by the smallest heuristic distance to any of the goals, which is admissible
+/// whenever the heuristic is, so any of the heuristics in this crate can be used, including
+/// [`AllPairsShortestPaths`](crate::pathfinding::AllPairsShortestPaths). Since every goal is
+/// considered each time a position is reached, for very many goals it can be faster to search
+/// without a heuristic using [`find_nearest_matching`].
+pub fn find_nearest_goal<H: Heuristic<C>, C: Coordinate, P: Passable<C> + ?Sized>(
+    heuristic: &H,
+    open_positions: &P,
+    start: C,
+    goals: &[C],
+) -> Option<(C, VecDeque<C>)> {
+    find_nearest_goal_with(heuristic, open_positions, start, goals, &VonNeumann)
+}
+
+/// Finds the shortest path to the nearest of the `goals` as in [`find_nearest_goal`], moving
+/// between positions according to the given [`Neighborhood`].
+pub fn find_nearest_goal_with<
+    H: Heuristic<C>,
+    C: Node,
+    P: Passable<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    heuristic: &H,
+    open_positions: &P,
+    start: C,
+    goals: &[C],
+    neighborhood: &N,
+) -> Option<(C, VecDeque<C>)> {
+    let goals: Vec<C> = goals
+        .iter()
+        .copied()
+        .filter(|goal| open_positions.is_passable(*goal))
+        .collect();
+    find_cheapest_goal_with(
+        heuristic,
+        &UnitCost(open_positions),
+        start,
+        &goals,
+        neighborhood,
+    )
+    .map(|(goal, path, _cost)| (goal, path))
+}
+
+/// Finds the cheapest path from `start` to whichever of the `goals` is cheapest to reach, where the
+/// cost of each step is given by the [`MovementCost`] map, returning that goal along with the path
+/// to it and its total cost. As with [`Heuristic::find_cheapest_path`], the heuristic must never
+/// overestimate the total cost of reaching a goal.
+pub fn find_cheapest_goal<H: Heuristic<C>, C: Coordinate, M: MovementCost<C> + ?Sized>(
+    heuristic: &H,
+    costs: &M,
+    start: C,
+    goals: &[C],
+) -> Option<(C, VecDeque<C>, u64)> {
+    find_cheapest_goal_with(heuristic, costs, start, goals, &VonNeumann)
+}
+
+/// Finds the cheapest path to the cheapest of the `goals` as in [`find_cheapest_goal`], moving
+/// between positions according to the given [`Neighborhood`].
+pub fn find_cheapest_goal_with<
+    H: Heuristic<C>,
+    C: Node,
+    M: MovementCost<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    heuristic: &H,
+    costs: &M,
+    start: C,
+    goals: &[C],
+    neighborhood: &N,
+) -> Option<(C, VecDeque<C>, u64)> {
+    let goal_set: HashSet<C> = goals.iter().copied().collect();
+    search(
+        costs,
+        start,
+        |position| goal_set.contains(&position),
+        |position| {
+            goals
+                .iter()
+                .map(|goal| heuristic.heuristic_distance(position, *goal))
+                .min()
+                .unwrap_or_default()
+        },
+        neighborhood,
+    )
+}
+
+/// Finds the shortest path from `start` to the nearest position for which `is_goal` returns true,
+/// such as the nearest tile with a tree on it, returning that position along with the path to it.
+/// Since nothing is known about where the goals are, positions are searched in order of their
+/// distance from the start.
+pub fn find_nearest_matching<C: Coordinate, P: Passable<C> + ?Sized, F: Fn(C) -> bool>(
+    open_positions: &P,
+    start: C,
+    is_goal: F,
+) -> Option<(C, VecDeque<C>)> {
+    find_nearest_matching_with(open_positions, start, is_goal, &VonNeumann)
+}
+
+/// Finds the shortest path to the nearest position matching `is_goal` as in
+/// [`find_nearest_matching`], moving between positions according to the given [`Neighborhood`].
+pub fn find_nearest_matching_with<
+    C: Node,
+    P: Passable<C> + ?Sized,
+    F: Fn(C) -> bool,
+    N: Neighborhood<C> + ?Sized,
+>(
+    open_positions: &P,
+    start: C,
+    is_goal: F,
+    neighborhood: &N,
+) -> Option<(C, VecDeque<C>)> {
+    search(
+        &UnitCost(open_positions),
+        start,
+        is_goal,
+        |_| WithInfinity::Normal(0),
+        neighborhood,
+    )
+    .map(|(goal, path, _cost)| (goal, path))
+}
+
+/// A* search from `start` to the first position popped which is a goal, guided by an estimate of
+/// the remaining cost to the nearest goal.
+fn search<C: Node, M: MovementCost<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+    costs: &M,
+    start: C,
+    is_goal: impl Fn(C) -> bool,
+    estimate: impl Fn(C) -> WithInfinity<u64>,
+    neighborhood: &N,
+) -> Option<(C, VecDeque<C>, u64)> {
+    let start_estimate = estimate(start);
+    if start_estimate == WithInfinity::Infinity {
+        return None;
+    }
+    let mut search = AStar::new(start, start_estimate);
+    while let Some((position, distance)) = search.pop() {
+        if is_goal(position) {
+            return Some((position, search.path_to(position), distance));
+        }
+        search.expand(costs, neighborhood, position, |neighbor, _| {
+            estimate(neighbor)
+        });
+    }
+
+    None
+}
+
+#[test]
+fn nearest_goal_test() {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        pathfinding::{all_pairs_shortest_paths, Dijkstra, HammingDistance},
+        test_util::assert_valid_path,
+        Position,
+    };
+
+    // A wall with a gap at the bottom, so the goal across it is nearer only as the crow flies.
+    let open_positions: BTreeSet<Position> = (0..10)
+        .flat_map(|x| (0..10).map(move |y| Position::new(x, y, 0)))
+        .filter(|position| position.x != 5 || position.y == 9)
+        .collect();
+    let all_pairs = all_pairs_shortest_paths(&open_positions);
+    let start = Position::new(4, 0, 0);
+    let (across, behind) = (Position::new(6, 0, 0), Position::new(0, 4, 0));
+    let goals = [across, behind, Position::new(5, 0, 0)];
+
+    for (goal, path) in [
+        find_nearest_goal(&HammingDistance, &open_positions, start, &goals),
+        find_nearest_goal(&all_pairs, &open_positions, start, &goals),
+        find_nearest_matching(&open_positions, start, |position| goals.contains(&position)),
+    ]
+    .into_iter()
+    .map(Option::unwrap)
+    {
+        assert_eq!(goal, behind);
+        assert_eq!(path.len(), 8);
+        assert_valid_path(&VonNeumann, &open_positions, start, path, behind);
+    }
+
+    // Crossing the wall costs nothing, so the goal across it is now the cheapest.
+    let costs = |_from: Position, to: Position| {
+        if to.x == 5 {
+            Some(0)
+        } else {
+            open_positions.contains(&to).then_some(1)
+        }
+    };
+    let (goal, path, cost) = find_cheapest_goal(&Dijkstra, &costs, start, &goals).unwrap();
+    assert_eq!((goal, path.len(), cost), (Position::new(5, 0, 0), 1, 0));
+
+    assert_eq!(
+        find_nearest_goal(&HammingDistance, &open_positions, start, &[start, across]),
+        Some((start, VecDeque::new()))
+    );
+    assert_eq!(
+        find_nearest_goal(
+            &all_pairs,
+            &open_positions,
+            start,
+            &[Position::new(20, 0, 0)]
+        ),
+        None
+    );
+    assert_eq!(
+        find_nearest_matching(&open_positions, start, |position| position.x > 10),
+        None
+    );
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::BuildHasher,
+};
+
+use itertools::Itertools;
+
+use crate::{Coordinate, Node, Position, Position2};
+
+/// A rule for which positions can be moved to in a single step from a given position. The
+/// searches in this crate default to [`VonNeumann`], which matches [`Coordinate::adjacent`], but
+/// each of them has a variant which accepts any [`Neighborhood`].
+///
+/// Maps from each [`Node`] to a list of its neighbors are also neighborhoods, which makes it easy
+/// to search over arbitrary graphs:
+/// ```
+/// use std::collections::{BTreeMap, BTreeSet};
+///
+/// use positioning::{bfs::Bfs, neighborhood::Neighborhood};
+///
+/// let graph: BTreeMap<&str, Vec<&str>> = vec![
+///     ("hall", vec!["kitchen", "stairs"]),
+///     ("kitchen", vec!["hall"]),
+///     ("stairs", vec!["hall", "attic"]),
+///     ("attic", vec!["stairs"]),
+/// ]
+/// .into_iter()
+/// .collect();
+/// let rooms: BTreeSet<&str> = graph.keys().copied().collect();
+///
+/// assert!(graph.are_adjacent("stairs", "attic"));
+/// assert_eq!(
+///     Bfs::with_neighborhood(&rooms, "kitchen", &graph).last(),
+///     Some(("attic", 3))
+/// );
+/// ```
+///
+/// # Symmetry
+///
+/// Searches which work backwards from their end, and maps which are built outwards from their
+/// goals but followed back towards them, need the neighborhood to be symmetric, so that each
+/// position is among the neighbors of its own neighbors. Every neighborhood in this crate is, and a
+/// graph is as long as each of its edges is listed from both ends.
+pub trait Neighborhood<C: Node = Position> {
+    /// Returns an iterator over all positions reachable in a single step from `position`.
+    fn neighbors(&self, position: C) -> Box<dyn Iterator<Item = C>>;
+
+    /// Returns whether or not `other` can be reached in a single step from `position`.
+    fn are_adjacent(&self, position: C, other: C) -> bool {
+        self.neighbors(position).any(|neighbor| neighbor == other)
+    }
+}
+
+fn offsets<F: Fn(i64, i64, i64) -> bool + 'static>(
+    position: Position,
+    include: F,
+) -> Box<dyn Iterator<Item = Position>> {
+    Box::new(
+        (-1..=1)
+            .cartesian_product(-1..=1)
+            .cartesian_product(-1..=1)
+            .filter(move |((dx, dy), dz)| include(*dx, *dy, *dz))
+            .map(move |((dx, dy), dz)| position + Position::new(dx, dy, dz)),
+    )
+}
+
+fn moved_axes(dx: i64, dy: i64, dz: i64) -> u32 {
+    (dx != 0) as u32 + (dy != 0) as u32 + (dz != 0) as u32
+}
+
+/// The positions given by [`Coordinate::adjacent`]. For a [`Position`], these are the 6 positions
+/// sharing a face with it, and for a [`Position2`], the 4 positions sharing an edge with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VonNeumann;
+
+impl<C: Coordinate> Neighborhood<C> for VonNeumann {
+    fn neighbors(&self, position: C) -> Box<dyn Iterator<Item = C>> {
+        position.adjacent()
+    }
+
+    fn are_adjacent(&self, position: C, other: C) -> bool {
+        position.is_adjacent_to(other)
+    }
+}
+
+/// The 18 positions sharing a face or an edge with a given position, allowing diagonal movement
+/// along two axes at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EdgeDiagonal;
+
+impl Neighborhood<Position> for EdgeDiagonal {
+    fn neighbors(&self, position: Position) -> Box<dyn Iterator<Item = Position>> {
+        offsets(position, |dx, dy, dz| {
+            (1..=2).contains(&moved_axes(dx, dy, dz))
+        })
+    }
+}
+
+/// All positions surrounding a given position, allowing diagonal movement along any axes. For a
+/// [`Position`] there are 26 of these, and for a [`Position2`] there are 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Moore;
+
+impl Neighborhood<Position> for Moore {
+    fn neighbors(&self, position: Position) -> Box<dyn Iterator<Item = Position>> {
+        offsets(position, |dx, dy, dz| moved_axes(dx, dy, dz) != 0)
+    }
+
+    fn are_adjacent(&self, position: Position, other: Position) -> bool {
+        position.chebyshev_distance(other) == 1
+    }
+}
+
+impl Neighborhood<Position2> for Moore {
+    fn neighbors(&self, position: Position2) -> Box<dyn Iterator<Item = Position2>> {
+        Box::new(
+            (-1..=1)
+                .cartesian_product(-1..=1)
+                .filter(|(dx, dy)| *dx != 0 || *dy != 0)
+                .map(move |(dx, dy)| position + Position2::new(dx, dy)),
+        )
+    }
+
+    fn are_adjacent(&self, position: Position2, other: Position2) -> bool {
+        position.chebyshev_distance(other) == 1
+    }
+}
+
+/// The 4 positions sharing an edge with a given position within its xy plane, keeping z fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PlanarVonNeumann;
+
+impl Neighborhood<Position> for PlanarVonNeumann {
+    fn neighbors(&self, position: Position) -> Box<dyn Iterator<Item = Position>> {
+        offsets(position, |dx, dy, dz| {
+            dz == 0 && moved_axes(dx, dy, dz) == 1
+        })
+    }
+}
+
+/// The 8 positions surrounding a given position within its xy plane, keeping z fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PlanarMoore;
+
+impl Neighborhood<Position> for PlanarMoore {
+    fn neighbors(&self, position: Position) -> Box<dyn Iterator<Item = Position>> {
+        offsets(position, |dx, dy, dz| {
+            dz == 0 && moved_axes(dx, dy, dz) != 0
+        })
+    }
+}
+
+/// Each node is adjacent to the nodes in its list. Nodes without an entry have no neighbors.
+impl<C: Node> Neighborhood<C> for BTreeMap<C, Vec<C>> {
+    fn neighbors(&self, position: C) -> Box<dyn Iterator<Item = C>> {
+        Box::new(self.get(&position).cloned().unwrap_or_default().into_iter())
+    }
+}
+
+/// Each node is adjacent to the nodes in its list. Nodes without an entry have no neighbors.
+impl<C: Node, S: BuildHasher> Neighborhood<C> for HashMap<C, Vec<C>, S> {
+    fn neighbors(&self, position: C) -> Box<dyn Iterator<Item = C>> {
+        Box::new(self.get(&position).cloned().unwrap_or_default().into_iter())
+    }
+}
+
+#[test]
+fn neighborhood_test() {
+    let origin = Position::new(0, 0, 0);
+    let neighborhoods: [(&dyn Neighborhood, usize); 5] = [
+        (&VonNeumann, 6),
+        (&EdgeDiagonal, 18),
+        (&Moore, 26),
+        (&PlanarVonNeumann, 4),
+        (&PlanarMoore, 8),
+    ];
+    for (neighborhood, count) in neighborhoods {
+        let neighbors: Vec<Position> = neighborhood.neighbors(origin).collect();
+        assert_eq!(neighbors.len(), count);
+        assert_eq!(neighbors.iter().unique().count(), count);
+        for neighbor in neighbors {
+            assert!(neighborhood.are_adjacent(origin, neighbor));
+            assert!(neighborhood.are_adjacent(neighbor, origin));
+            assert_eq!(origin.chebyshev_distance(neighbor), 1);
+        }
+        assert!(!neighborhood.are_adjacent(origin, origin));
+    }
+    assert!(EdgeDiagonal.are_adjacent(origin, Position::new(1, 0, -1)));
+    assert!(!EdgeDiagonal.are_adjacent(origin, Position::new(1, 1, -1)));
+    assert!(PlanarMoore.are_adjacent(origin, Position::new(1, -1, 0)));
+    assert!(!PlanarMoore.are_adjacent(origin, Position::new(0, 0, 1)));
+
+    let origin = Position2::new(0, 0);
+    assert_eq!(VonNeumann.neighbors(origin).count(), 4);
+    assert_eq!(
+        Neighborhood::<Position2>::neighbors(&Moore, origin)
+            .map(|neighbor| neighbor.extend(0))
+            .collect::<Vec<_>>(),
+        PlanarMoore
+            .neighbors(Position::new(0, 0, 0))
+            .collect::<Vec<_>>()
+    );
+}
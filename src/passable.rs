@@ -0,0 +1,156 @@
+//! # Passable
+//!
+//! Abstractions over the maps which the searches in this crate run over. Rather than requiring a
+//! [`BTreeSet`] of open positions, searches only need to be able to ask whether or not a given
+//! position can be moved through, or for weighted searches, how much it costs to do so. Each trait
+//! is generic over the [`Node`]s being searched, and defaults to working with [`Position`]s.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::BuildHasher,
+};
+
+use crate::{Node, Position};
+
+/// A map which knows whether or not any given position can be moved through.
+///
+/// This is implemented for sets of open positions, as well as for any closure from a
+/// [`Node`] to a [`bool`]:
+/// ```
+/// use positioning::{Passable, Position};
+///
+/// let below_ground = |position: Position| position.z < 0;
+/// assert!(below_ground.is_passable(Position::new(0, 0, -1)));
+/// assert!(!below_ground.is_passable(Position::new(0, 0, 0)));
+/// ```
+pub trait Passable<C: Node = Position> {
+    /// Returns whether or not the given position can be moved through.
+    fn is_passable(&self, position: C) -> bool;
+}
+
+/// A [`Passable`] map which is also able to enumerate all of its passable positions. This is
+/// required by precomputations which need to visit every open position, such as
+/// [`all_pairs_shortest_paths`](crate::pathfinding::all_pairs_shortest_paths).
+pub trait PassableSet<C: Node = Position>: Passable<C> {
+    /// Returns an iterator over all passable positions, in no particular order.
+    fn passable_positions(&self) -> Box<dyn Iterator<Item = C> + '_>;
+}
+
+impl<C: Node> Passable<C> for BTreeSet<C> {
+    fn is_passable(&self, position: C) -> bool {
+        self.contains(&position)
+    }
+}
+
+impl<C: Node> PassableSet<C> for BTreeSet<C> {
+    fn passable_positions(&self) -> Box<dyn Iterator<Item = C> + '_> {
+        Box::new(self.iter().copied())
+    }
+}
+
+impl<C: Node, S: BuildHasher> Passable<C> for HashSet<C, S> {
+    fn is_passable(&self, position: C) -> bool {
+        self.contains(&position)
+    }
+}
+
+impl<C: Node, S: BuildHasher> PassableSet<C> for HashSet<C, S> {
+    fn passable_positions(&self) -> Box<dyn Iterator<Item = C> + '_> {
+        Box::new(self.iter().copied())
+    }
+}
+
+impl<C: Node, F: Fn(C) -> bool> Passable<C> for F {
+    fn is_passable(&self, position: C) -> bool {
+        self(position)
+    }
+}
+
+/// A map which knows how much it costs to move between adjacent positions, used for weighted
+/// pathfinding.
+///
+/// This is implemented for maps from each open [`Node`] to the cost of stepping onto it, as
+/// well as for any closure taking the position being left and the position being entered, which
+/// allows costs to depend on the direction of movement.
+pub trait MovementCost<C: Node = Position> {
+    /// Returns the cost of stepping from `from` onto the adjacent position `to`, or [`None`] if
+    /// `to` cannot be moved through.
+    fn movement_cost(&self, from: C, to: C) -> Option<u64>;
+}
+
+/// Treats every step onto a [`Passable`] position as costing exactly 1.
+pub struct UnitCost<'a, P: ?Sized>(pub &'a P);
+
+impl<'a, C: Node, P: Passable<C> + ?Sized> MovementCost<C> for UnitCost<'a, P> {
+    fn movement_cost(&self, _from: C, to: C) -> Option<u64> {
+        if self.0.is_passable(to) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+impl<C: Node> MovementCost<C> for BTreeMap<C, u64> {
+    fn movement_cost(&self, _from: C, to: C) -> Option<u64> {
+        self.get(&to).copied()
+    }
+}
+
+impl<C: Node, S: BuildHasher> MovementCost<C> for HashMap<C, u64, S> {
+    fn movement_cost(&self, _from: C, to: C) -> Option<u64> {
+        self.get(&to).copied()
+    }
+}
+
+impl<C: Node, F: Fn(C, C) -> Option<u64>> MovementCost<C> for F {
+    fn movement_cost(&self, from: C, to: C) -> Option<u64> {
+        self(from, to)
+    }
+}
+
+#[test]
+fn passable_test() {
+    let open_positions: BTreeSet<Position> = vec![Position::new(0, 0, 0), Position::new(1, 0, 0)]
+        .into_iter()
+        .collect();
+    let hashed: HashSet<Position> = open_positions.iter().copied().collect();
+    let closure = |position: Position| open_positions.contains(&position);
+
+    for x in -1..=2 {
+        let position = Position::new(x, 0, 0);
+        assert_eq!(open_positions.is_passable(position), x == 0 || x == 1);
+        assert_eq!(hashed.is_passable(position), x == 0 || x == 1);
+        assert_eq!(closure.is_passable(position), x == 0 || x == 1);
+    }
+
+    let mut enumerated: Vec<Position> = hashed.passable_positions().collect();
+    enumerated.sort();
+    assert_eq!(
+        enumerated,
+        open_positions.passable_positions().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn movement_cost_test() {
+    let open_positions: BTreeSet<Position> = vec![Position::new(0, 0, 0), Position::new(0, 0, 1)]
+        .into_iter()
+        .collect();
+    let costs: HashMap<Position, u64> = open_positions
+        .iter()
+        .map(|p| (*p, p.z as u64 + 3))
+        .collect();
+    let climbing = |from: Position, to: Position| Some(if to.z > from.z { 5 } else { 1 });
+
+    let (a, b) = (Position::new(0, 0, 0), Position::new(0, 0, 1));
+    assert_eq!(UnitCost(&open_positions).movement_cost(a, b), Some(1));
+    assert_eq!(
+        UnitCost(&open_positions).movement_cost(a, Position::new(1, 0, 0)),
+        None
+    );
+    assert_eq!(costs.movement_cost(a, b), Some(4));
+    assert_eq!(costs.movement_cost(b, a), Some(3));
+    assert_eq!(climbing.movement_cost(a, b), Some(5));
+    assert_eq!(climbing.movement_cost(b, a), Some(1));
+}
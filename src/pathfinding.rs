@@ -8,27 +8,39 @@
 //! Then, we can compute all-pairs shortest paths on the statically known paths and use that
 //! information in order to inform a clever heuristic for the A* algorithm. That is the strategy
 //! taken in this module.
+//!
+//! ## Changing Maps
+//!
+//! Some of the structures in this crate, such as the
+//! [`HierarchicalMap`](crate::hierarchical::HierarchicalMap), keep the results of earlier work
+//! around in between calls. They don't hold on to the map themselves, so each of their methods
+//! takes it again, and it is expected to be the same map every time, with the structure told about
+//! every tile which has changed since the last call. Otherwise their results describe a map which
+//! no longer exists.
 
 /// A number which has an Infinity variant in order to indicate that there is no path. It is
 /// convenient to be consistent with the pathfinding literature by using this type, otherwise I
 /// have to diverge from the algorithms as written up occasionally more than I'd like.
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, VecDeque},
+};
 
 use priority_queue::DoublePriorityQueue; // TODO Replace with PriorityQueue<_, Reverse<_>>
 
-use crate::position::Position;
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Copy, Clone)]
+use crate::{
+    hex::Hex,
+    neighborhood::{Neighborhood, VonNeumann},
+    position::Position,
+    Coordinate, MovementCost, Node, Passable, PassableSet, Position2, UnitCost,
+};
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Copy, Clone, Default)]
 pub enum WithInfinity<I> {
     Normal(I),
+    #[default]
     Infinity,
 }
 
-impl<I> Default for WithInfinity<I> {
-    fn default() -> Self {
-        WithInfinity::Infinity
-    }
-}
-
 impl<I: std::ops::Add<I, Output = I>> std::ops::Add<WithInfinity<I>> for WithInfinity<I> {
     type Output = WithInfinity<I>;
 
@@ -56,85 +68,431 @@ fn with_infinity_test() {
     }
 }
 
+/// Statistics about the work done by a search, for debugging and profiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchStats {
+    /// The number of positions whose neighbors were searched.
+    pub nodes_expanded: usize,
+    /// The largest number of positions waiting to be expanded at any one time.
+    pub max_frontier: usize,
+}
+
+/// A path found by one of the `try_find` searches of a [`Heuristic`], along with its total cost
+/// and the work it took to find.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathResult<C = Position> {
+    /// The path, which as usual excludes the start and includes the end.
+    pub path: VecDeque<C>,
+    /// The total cost of the path, which is its length for the shortest path searches.
+    pub cost: u64,
+    /// The work done by the search that found this path.
+    pub stats: SearchStats,
+}
+
+/// The reason one of the `try_find` searches of a [`Heuristic`] failed to find a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// The end is not passable, so no search was done.
+    EndBlocked,
+    /// The heuristic reported that the end cannot be reached from the start, so no search was
+    /// done.
+    HeuristicUnreachable,
+    /// Every position reachable from the start was searched without finding the end.
+    Exhausted(SearchStats),
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::EndBlocked => write!(f, "the end is blocked"),
+            PathError::HeuristicUnreachable => {
+                write!(f, "the heuristic says the end is unreachable")
+            }
+            PathError::Exhausted(stats) => write!(
+                f,
+                "no path found after expanding {} positions",
+                stats.nodes_expanded
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// The state of an A* search from a single start, shared by the searches which decide for
+/// themselves when to stop.
+pub(crate) struct AStar<C: Node> {
+    start: C,
+    distances: BTreeMap<C, u64>,
+    predecessor: BTreeMap<C, C>,
+    queue: DoublePriorityQueue<C, WithInfinity<u64>>,
+    pub(crate) stats: SearchStats,
+}
+
+impl<C: Node> AStar<C> {
+    /// Starts a search from `start`, with the given estimate of the total cost through it.
+    pub(crate) fn new(start: C, estimate: WithInfinity<u64>) -> Self {
+        let mut search = AStar {
+            start,
+            distances: BTreeMap::new(),
+            predecessor: BTreeMap::new(),
+            queue: DoublePriorityQueue::new(),
+            stats: SearchStats::default(),
+        };
+        search.distances.insert(start, 0);
+        search.queue.push(start, estimate);
+        search
+    }
+
+    /// Takes the position with the lowest estimate off the queue, along with the cost of the
+    /// cheapest path to it.
+    pub(crate) fn pop(&mut self) -> Option<(C, u64)> {
+        self.stats.max_frontier = self.stats.max_frontier.max(self.queue.len());
+        let (position, _estimate) = self.queue.pop_min()?;
+        Some((position, self.distances[&position]))
+    }
+
+    /// Searches the neighbors of `position`, queueing each one which can be reached more cheaply
+    /// through it. The `remaining` cost from a neighbor is estimated given the cost of reaching it,
+    /// and neighbors for which that is infinite are skipped.
+    pub(crate) fn expand<M: MovementCost<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        &mut self,
+        costs: &M,
+        neighborhood: &N,
+        position: C,
+        mut remaining: impl FnMut(C, u64) -> WithInfinity<u64>,
+    ) {
+        self.stats.nodes_expanded += 1;
+        let distance = self.distances[&position];
+        for neighbor in neighborhood.neighbors(position) {
+            let cost = match costs.movement_cost(position, neighbor) {
+                Some(cost) => cost,
+                None => continue,
+            };
+            let alt = distance + cost;
+            if self.distances.get(&neighbor).is_none_or(|best| alt < *best) {
+                let estimate = WithInfinity::Normal(alt) + remaining(neighbor, alt);
+                if estimate == WithInfinity::Infinity {
+                    continue;
+                }
+                self.distances.insert(neighbor, alt);
+                self.predecessor.insert(neighbor, position);
+                if self.queue.change_priority(&neighbor, estimate).is_none() {
+                    self.queue.push(neighbor, estimate);
+                }
+            }
+        }
+    }
+
+    /// The cheapest path found so far from the start to `position`, which must have been reached.
+    pub(crate) fn path_to(&self, position: C) -> VecDeque<C> {
+        let mut path = VecDeque::new();
+        let mut current_position = position;
+        while current_position != self.start {
+            path.push_front(current_position);
+            current_position = *self
+                .predecessor
+                .get(&current_position)
+                .expect("should always have a path home");
+        }
+        path
+    }
+}
+
 /// An admissible heuristic for the A* pathfinding algorithm is one which always returns an
 /// optimistic result. Oftentimes, a good heuristic can make a big performance difference. The more
 /// tight the heuristic is to the real distance, the better.
-pub trait Heuristic {
-    fn heuristic_distance(&self, start: Position, end: Position) -> WithInfinity<u64>;
+///
+/// Heuristics are defined over some [`Node`] type, which defaults to [`Position`]. The searches
+/// below work over any [`Coordinate`] type, and the variants taking a [`Neighborhood`] work over
+/// any [`Node`] type at all.
+pub trait Heuristic<C: Node = Position> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64>;
 
-    fn find_shortest_path(
+    fn find_shortest_path<P: Passable<C> + ?Sized>(
         &self,
-        open_positions: &BTreeSet<Position>,
-        start: Position,
-        end: Position,
-    ) -> Option<VecDeque<Position>> {
-        let mut distances_from_start: BTreeMap<Position, WithInfinity<u64>> = BTreeMap::new();
-        let mut predecessor: BTreeMap<Position, Position> = BTreeMap::new();
-        let mut queue: DoublePriorityQueue<Position, WithInfinity<u64>> =
-            DoublePriorityQueue::new();
-        distances_from_start.insert(start, WithInfinity::Normal(0));
-        match self.heuristic_distance(start, end) {
-            WithInfinity::Infinity => return None,
-            n => {
-                queue.push(start, n);
+        open_positions: &P,
+        start: C,
+        end: C,
+    ) -> Option<VecDeque<C>>
+    where
+        C: Coordinate,
+    {
+        self.find_shortest_path_with(open_positions, start, end, &VonNeumann)
+    }
+
+    /// Finds the shortest path from `start` to `end`, moving between positions according to the
+    /// given [`Neighborhood`]. The heuristic must be admissible for that neighborhood, so for
+    /// example [`HammingDistance`] should be replaced by [`ChebyshevDistance`] when moving
+    /// diagonally.
+    fn find_shortest_path_with<P: Passable<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        &self,
+        open_positions: &P,
+        start: C,
+        end: C,
+        neighborhood: &N,
+    ) -> Option<VecDeque<C>> {
+        self.try_find_shortest_path_with(open_positions, start, end, neighborhood)
+            .ok()
+            .map(|result| result.path)
+    }
+
+    /// Finds the shortest path from `start` to `end` as in [`Heuristic::find_shortest_path`],
+    /// reporting the work done by the search, or why it failed.
+    fn try_find_shortest_path<P: Passable<C> + ?Sized>(
+        &self,
+        open_positions: &P,
+        start: C,
+        end: C,
+    ) -> Result<PathResult<C>, PathError>
+    where
+        C: Coordinate,
+    {
+        self.try_find_shortest_path_with(open_positions, start, end, &VonNeumann)
+    }
+
+    /// Finds the shortest path from `start` to `end` as in [`Heuristic::find_shortest_path_with`],
+    /// reporting the work done by the search, or why it failed.
+    fn try_find_shortest_path_with<P: Passable<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        &self,
+        open_positions: &P,
+        start: C,
+        end: C,
+        neighborhood: &N,
+    ) -> Result<PathResult<C>, PathError> {
+        if !open_positions.is_passable(end) {
+            return Err(PathError::EndBlocked);
+        }
+
+        self.try_find_cheapest_path_with(&UnitCost(open_positions), start, end, neighborhood)
+    }
+
+    /// Finds the cheapest path from `start` to `end`, where the cost of each step is given by the
+    /// [`MovementCost`] map, returning the path alongside its total cost. For the result to be
+    /// optimal, the heuristic must never overestimate the total cost of reaching `end`, so the
+    /// plain [`HammingDistance`] should be replaced by a [`WeightedHammingDistance`] when steps
+    /// may cost more than 1.
+    fn find_cheapest_path<M: MovementCost<C> + ?Sized>(
+        &self,
+        costs: &M,
+        start: C,
+        end: C,
+    ) -> Option<(VecDeque<C>, u64)>
+    where
+        C: Coordinate,
+    {
+        self.find_cheapest_path_with(costs, start, end, &VonNeumann)
+    }
+
+    /// Finds the cheapest path from `start` to `end` as in [`Heuristic::find_cheapest_path`],
+    /// moving between positions according to the given [`Neighborhood`].
+    fn find_cheapest_path_with<M: MovementCost<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        &self,
+        costs: &M,
+        start: C,
+        end: C,
+        neighborhood: &N,
+    ) -> Option<(VecDeque<C>, u64)> {
+        self.try_find_cheapest_path_with(costs, start, end, neighborhood)
+            .ok()
+            .map(|result| (result.path, result.cost))
+    }
+
+    /// Finds the cheapest path from `start` to `end` as in [`Heuristic::find_cheapest_path`],
+    /// reporting the work done by the search, or why it failed.
+    fn try_find_cheapest_path<M: MovementCost<C> + ?Sized>(
+        &self,
+        costs: &M,
+        start: C,
+        end: C,
+    ) -> Result<PathResult<C>, PathError>
+    where
+        C: Coordinate,
+    {
+        self.try_find_cheapest_path_with(costs, start, end, &VonNeumann)
+    }
+
+    /// Finds the cheapest path from `start` to `end` as in
+    /// [`Heuristic::find_cheapest_path_with`], reporting the work done by the search, or why it
+    /// failed.
+    fn try_find_cheapest_path_with<M: MovementCost<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        &self,
+        costs: &M,
+        start: C,
+        end: C,
+        neighborhood: &N,
+    ) -> Result<PathResult<C>, PathError> {
+        let estimate = self.heuristic_distance(start, end);
+        if estimate == WithInfinity::Infinity {
+            return Err(PathError::HeuristicUnreachable);
+        }
+        let mut search = AStar::new(start, estimate);
+        while let Some((position, distance)) = search.pop() {
+            if position == end {
+                return Ok(PathResult {
+                    path: search.path_to(end),
+                    cost: distance,
+                    stats: search.stats,
+                });
             }
+            search.expand(costs, neighborhood, position, |neighbor, _| {
+                self.heuristic_distance(neighbor, end)
+            });
         }
+        Err(PathError::Exhausted(search.stats))
+    }
 
-        if !open_positions.contains(&end) {
+    /// Finds the shortest path from `start` to `end` as in [`Heuristic::find_shortest_path`], but
+    /// searches backwards from `end` at the same time as forwards from `start`, stopping once the
+    /// searches have met along a path which neither of them can improve on. Along long corridors,
+    /// this expands roughly half as many positions.
+    fn find_shortest_path_bidirectional<P: Passable<C> + ?Sized>(
+        &self,
+        open_positions: &P,
+        start: C,
+        end: C,
+    ) -> Option<VecDeque<C>>
+    where
+        C: Coordinate,
+    {
+        self.find_shortest_path_bidirectional_with(open_positions, start, end, &VonNeumann)
+    }
+
+    /// Finds the shortest path from `start` to `end` as in
+    /// [`Heuristic::find_shortest_path_bidirectional`], moving between positions according to the
+    /// given [`Neighborhood`], which must be [symmetric](Neighborhood#symmetry).
+    fn find_shortest_path_bidirectional_with<
+        P: Passable<C> + ?Sized,
+        N: Neighborhood<C> + ?Sized,
+    >(
+        &self,
+        open_positions: &P,
+        start: C,
+        end: C,
+        neighborhood: &N,
+    ) -> Option<VecDeque<C>> {
+        if !open_positions.is_passable(end) {
             return None;
         }
 
-        loop {
-            match queue.pop_min() {
-                None => {
+        self.find_cheapest_path_bidirectional_with(
+            &UnitCost(open_positions),
+            start,
+            end,
+            neighborhood,
+        )
+        .map(|(path, _cost)| path)
+    }
+
+    /// Finds the cheapest path from `start` to `end` as in [`Heuristic::find_cheapest_path`],
+    /// searching from both ends at once as in [`Heuristic::find_shortest_path_bidirectional`].
+    fn find_cheapest_path_bidirectional<M: MovementCost<C> + ?Sized>(
+        &self,
+        costs: &M,
+        start: C,
+        end: C,
+    ) -> Option<(VecDeque<C>, u64)>
+    where
+        C: Coordinate,
+    {
+        self.find_cheapest_path_bidirectional_with(costs, start, end, &VonNeumann)
+    }
+
+    /// Finds the cheapest path from `start` to `end` as in
+    /// [`Heuristic::find_cheapest_path_bidirectional`], moving between positions according to the
+    /// given symmetric [`Neighborhood`]. Costs may still depend on the direction of movement.
+    fn find_cheapest_path_bidirectional_with<
+        M: MovementCost<C> + ?Sized,
+        N: Neighborhood<C> + ?Sized,
+    >(
+        &self,
+        costs: &M,
+        start: C,
+        end: C,
+        neighborhood: &N,
+    ) -> Option<(VecDeque<C>, u64)> {
+        // Index 0 is the search forwards from the start, and index 1 the search backwards from the
+        // end, whose links point towards the end rather than towards the start.
+        let mut distances: [BTreeMap<C, WithInfinity<u64>>; 2] = Default::default();
+        let mut links: [BTreeMap<C, C>; 2] = Default::default();
+        let mut queues: [DoublePriorityQueue<C, WithInfinity<u64>>; 2] = Default::default();
+        match self.heuristic_distance(start, end) {
+            WithInfinity::Infinity => return None,
+            n => {
+                queues[0].push(start, n);
+                queues[1].push(end, n);
+            }
+        }
+        distances[0].insert(start, WithInfinity::Normal(0));
+        distances[1].insert(end, WithInfinity::Normal(0));
+        let mut best: Option<(u64, C)> = (start == end).then_some((0, start));
+
+        while let (Some((_, forward)), Some((_, backward))) =
+            (queues[0].peek_min(), queues[1].peek_min())
+        {
+            let (forward, backward) = (*forward, *backward);
+            if let Some((cost, _)) = best {
+                if forward >= WithInfinity::Normal(cost) || backward >= WithInfinity::Normal(cost) {
                     break;
                 }
-                Some((position, _distance)) => {
-                    if position == end {
-                        break;
+            }
+
+            let side = if queues[0].len() <= queues[1].len() {
+                0
+            } else {
+                1
+            };
+            let (position, _estimate) = queues[side].pop_min().expect("queue is nonempty");
+            let distance = distances[side].get(&position).copied().unwrap_or_default();
+            for neighbor in neighborhood.neighbors(position) {
+                let cost = match if side == 0 {
+                    costs.movement_cost(position, neighbor)
+                } else {
+                    costs.movement_cost(neighbor, position)
+                } {
+                    Some(cost) => cost,
+                    None => continue,
+                };
+                let alt = distance + WithInfinity::Normal(cost);
+                if alt < *distances[side].entry(neighbor).or_default() {
+                    let halt = alt
+                        + if side == 0 {
+                            self.heuristic_distance(neighbor, end)
+                        } else {
+                            self.heuristic_distance(start, neighbor)
+                        };
+                    if halt == WithInfinity::Infinity {
+                        continue;
                     }
-                    for neighbor in position
-                        .adjacent()
-                        .filter(|neighbor| open_positions.contains(neighbor))
+                    distances[side].insert(neighbor, alt);
+                    links[side].insert(neighbor, position);
+                    if queues[side].change_priority(&neighbor, halt).is_none() {
+                        queues[side].push(neighbor, halt);
+                    }
+                    if let (WithInfinity::Normal(alt), Some(WithInfinity::Normal(other))) =
+                        (alt, distances[1 - side].get(&neighbor))
                     {
-                        let alt = distances_from_start
-                            .get(&position)
-                            .map(|e| *e)
-                            .unwrap_or_default()
-                            + WithInfinity::Normal(1);
-                        if alt < *distances_from_start.entry(neighbor).or_default() {
-                            let halt = alt + self.heuristic_distance(position, end);
-                            distances_from_start.insert(neighbor, alt);
-                            predecessor.insert(neighbor, position);
-                            if queue.change_priority(&neighbor, halt).is_none() {
-                                queue.push(neighbor, halt);
-                            }
+                        if best.is_none_or(|(cost, _)| alt + other < cost) {
+                            best = Some((alt + other, neighbor));
                         }
                     }
                 }
             }
         }
 
-        match distances_from_start.entry(end).or_default() {
-            WithInfinity::Infinity => None,
-            WithInfinity::Normal(distance) => {
-                let mut current_position = end;
-                let mut path = VecDeque::new();
-                loop {
-                    if current_position == start {
-                        break;
-                    }
-                    path.push_front(current_position);
-                    if let Some(pre) = predecessor.get(&current_position) {
-                        current_position = *pre;
-                    } else {
-                        panic!("should always have a path home");
-                    }
-                }
-                assert_eq!(*distance, path.len() as u64);
-                Some(path)
-            }
+        let (cost, meeting) = best?;
+        let mut path = VecDeque::new();
+        let mut current_position = meeting;
+        while current_position != start {
+            path.push_front(current_position);
+            current_position = links[0][&current_position];
+        }
+        current_position = meeting;
+        while current_position != end {
+            current_position = links[1][&current_position];
+            path.push_back(current_position);
         }
+        Some((path, cost))
     }
 }
 
@@ -161,15 +519,44 @@ pub trait Heuristic {
 ///
 /// **Correctness**: When you use this heuristic, you should be careful to ensure that the dynamically
 /// open positions are a subset of the statically open positions.
-pub struct AllPairsShortestPaths(BTreeMap<(Position, Position), WithInfinity<u64>>);
+///
+/// **Memory**: Each open position is assigned a dense [`u32`] index, and the distances are stored
+/// in a flat matrix of [`u32`]s indexed by pairs of these, so the table takes roughly four bytes
+/// per pair of open positions. Distances too large to fit are clamped, which keeps the heuristic
+/// admissible but means [`AllPairsShortestPaths::distance_between`] only reports a lower bound for
+/// them.
+pub struct AllPairsShortestPaths<C = Position> {
+    indices: HashMap<C, u32>,
+    distances: Vec<u32>,
+}
 
-impl Heuristic for AllPairsShortestPaths {
-    fn heuristic_distance(&self, start: Position, end: Position) -> WithInfinity<u64> {
+impl<C: Node> Heuristic<C> for AllPairsShortestPaths<C> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
         self.distance_between(start, end).unwrap_or_default()
     }
 }
 
-/// The 3D hamming distance metric is always fine to use.
+/// Uses no information at all, turning A* into Dijkstra's algorithm. This is admissible for any
+/// [`Node`] type, which makes it useful for searching graphs which have no notion of distance.
+pub struct Dijkstra;
+
+impl<C: Node> Heuristic<C> for Dijkstra {
+    fn heuristic_distance(&self, _start: C, _end: C) -> WithInfinity<u64> {
+        WithInfinity::Normal(0)
+    }
+}
+
+/// The [`Coordinate::distance`] between two coordinates is admissible for any [`Coordinate`]
+/// type, as long as movement follows [`Coordinate::adjacent`] and each step costs at least 1.
+pub struct CoordinateDistance;
+
+impl<C: Coordinate> Heuristic<C> for CoordinateDistance {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        WithInfinity::Normal(start.distance(end))
+    }
+}
+
+/// The 3D hamming distance metric is always fine to use, as is its 2D counterpart.
 pub struct HammingDistance;
 
 impl Heuristic for HammingDistance {
@@ -178,49 +565,692 @@ impl Heuristic for HammingDistance {
     }
 }
 
-impl AllPairsShortestPaths {
-    pub fn distance_between(
-        &self,
-        position: Position,
-        other_position: Position,
-    ) -> Option<WithInfinity<u64>> {
-        self.0.get(&(position, other_position)).map(|p| *p)
+impl Heuristic<Position2> for HammingDistance {
+    fn heuristic_distance(&self, start: Position2, end: Position2) -> WithInfinity<u64> {
+        WithInfinity::Normal(start.hamming_distance(end))
     }
 }
 
-/// Computes a data structure caching the distances between all open positions
-pub fn all_pairs_shortest_paths(open_positions: &BTreeSet<Position>) -> AllPairsShortestPaths {
-    let mut distances = BTreeMap::new();
-    for position in open_positions.iter().copied() {
-        for adjacent in position.adjacent() {
-            distances.insert((position, adjacent), WithInfinity::Normal(1));
+/// The number of steps between two hexes on an open hex grid is always fine to use for
+/// pathfinding over [`Hex`]es.
+pub struct HexDistance;
+
+impl Heuristic<Hex> for HexDistance {
+    fn heuristic_distance(&self, start: Hex, end: Hex) -> WithInfinity<u64> {
+        WithInfinity::Normal(start.hex_distance(end))
+    }
+}
+
+/// The 3D hamming distance metric scaled by the smallest cost of any single step, which keeps it
+/// admissible for [`Heuristic::find_cheapest_path`] over a [`MovementCost`] map in which no step
+/// costs less than `minimum_cost`.
+pub struct WeightedHammingDistance {
+    pub minimum_cost: u64,
+}
+
+impl Heuristic for WeightedHammingDistance {
+    fn heuristic_distance(&self, start: Position, end: Position) -> WithInfinity<u64> {
+        WithInfinity::Normal(
+            start
+                .hamming_distance(end)
+                .saturating_mul(self.minimum_cost),
+        )
+    }
+}
+
+impl Heuristic<Position2> for WeightedHammingDistance {
+    fn heuristic_distance(&self, start: Position2, end: Position2) -> WithInfinity<u64> {
+        WithInfinity::Normal(
+            start
+                .hamming_distance(end)
+                .saturating_mul(self.minimum_cost),
+        )
+    }
+}
+
+/// The 3D Chebyshev distance, the largest difference along any single axis, is admissible for
+/// every [`Neighborhood`] in this crate when each step costs 1, and is exact for
+/// [`Moore`](crate::neighborhood::Moore) movement on an open grid.
+pub struct ChebyshevDistance;
+
+impl Heuristic for ChebyshevDistance {
+    fn heuristic_distance(&self, start: Position, end: Position) -> WithInfinity<u64> {
+        WithInfinity::Normal(start.chebyshev_distance(end))
+    }
+}
+
+impl Heuristic<Position2> for ChebyshevDistance {
+    fn heuristic_distance(&self, start: Position2, end: Position2) -> WithInfinity<u64> {
+        WithInfinity::Normal(start.chebyshev_distance(end))
+    }
+}
+
+/// The octile distance is the cost of moving between two positions over an open grid with
+/// [`Moore`](crate::neighborhood::Moore) movement, where straight steps cost `straight` and
+/// diagonal steps cost `diagonal`. In 3D, only the two axes along which the positions differ the
+/// most are counted, since a single diagonal step may move along all three. It is admissible for
+/// [`Heuristic::find_cheapest_path_with`] under any of the neighborhoods in this crate whenever no
+/// straight step costs less than `straight`, no diagonal step costs less than `diagonal`, and
+/// `diagonal` is between `straight` and twice `straight`.
+pub struct OctileDistance {
+    pub straight: u64,
+    pub diagonal: u64,
+}
+
+impl OctileDistance {
+    fn octile(&self, major: u64, minor: u64) -> WithInfinity<u64> {
+        WithInfinity::Normal(self.diagonal * minor + self.straight * (major - minor))
+    }
+}
+
+impl Heuristic for OctileDistance {
+    fn heuristic_distance(&self, start: Position, end: Position) -> WithInfinity<u64> {
+        let mut differences = [
+            start.x.abs_diff(end.x),
+            start.y.abs_diff(end.y),
+            start.z.abs_diff(end.z),
+        ];
+        differences.sort_unstable();
+        self.octile(differences[2], differences[1])
+    }
+}
+
+impl Heuristic<Position2> for OctileDistance {
+    fn heuristic_distance(&self, start: Position2, end: Position2) -> WithInfinity<u64> {
+        let dx = start.x.abs_diff(end.x);
+        let dy = start.y.abs_diff(end.y);
+        self.octile(dx.max(dy), dx.min(dy))
+    }
+}
+
+/// A reference to a heuristic is the same heuristic, which allows a large precomputed table to be
+/// shared between several of the combinators below.
+impl<C: Node, H: Heuristic<C> + ?Sized> Heuristic<C> for &H {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        (**self).heuristic_distance(start, end)
+    }
+}
+
+/// The pointwise maximum of two heuristics. If both are admissible, then so is their maximum, and
+/// it is at least as tight as either one of them alone:
+/// ```
+/// use std::collections::BTreeSet;
+///
+/// use positioning::{
+///     pathfinding::{all_pairs_shortest_paths, HammingDistance, Heuristic, Max},
+///     Position,
+/// };
+///
+/// let open_positions: BTreeSet<Position> = (0..4).map(|x| Position::new(x, 0, 0)).collect();
+/// let all_pairs = all_pairs_shortest_paths(&open_positions);
+/// let path = Max(&all_pairs, HammingDistance).find_shortest_path(
+///     &open_positions,
+///     Position::new(0, 0, 0),
+///     Position::new(3, 0, 0),
+/// );
+/// assert_eq!(path.map(|path| path.len()), Some(3));
+/// ```
+pub struct Max<A, B>(pub A, pub B);
+
+impl<C: Node, A: Heuristic<C>, B: Heuristic<C>> Heuristic<C> for Max<A, B> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        self.0
+            .heuristic_distance(start, end)
+            .max(self.1.heuristic_distance(start, end))
+    }
+}
+
+/// Multiplies a heuristic by a constant weight of `numerator / denominator`, rounding down. A
+/// weight above 1 makes the heuristic inadmissible but greedier, which is weighted A*: searches
+/// expand fewer positions, and the paths found by an admissible heuristic scaled by the weight cost
+/// at most that many times as much as the cheapest ones.
+///
+/// The weight is a fraction rather than a float so that scaling is exact for any distance. The
+/// `denominator` must not be 0, and distances which would no longer fit in a [`u64`] are treated
+/// as unreachable.
+pub struct Scaled<H> {
+    pub heuristic: H,
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl<C: Node, H: Heuristic<C>> Heuristic<C> for Scaled<H> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        assert!(
+            self.denominator != 0,
+            "the weight's denominator must not be 0"
+        );
+        match self.heuristic.heuristic_distance(start, end) {
+            WithInfinity::Normal(distance) => {
+                let scaled = distance as u128 * self.numerator as u128 / self.denominator as u128;
+                u64::try_from(scaled).map_or(WithInfinity::Infinity, WithInfinity::Normal)
+            }
+            WithInfinity::Infinity => WithInfinity::Infinity,
         }
     }
-    for position in open_positions.iter().copied() {
-        for other_position in open_positions.iter().copied() {
-            let _ = distances
-                .entry((position, other_position))
-                .or_insert(WithInfinity::Infinity);
+}
+
+/// Uses a precomputed `table`, either an [`AllPairsShortestPaths`] or a [`Landmarks`] heuristic,
+/// for positions which were open when it was computed, and the `fallback` heuristic for any
+/// others, or for every query once `diverged` is set.
+///
+/// The table is only admissible while every open position was also open when it was computed.
+/// Opening any other position, for example by digging out a tile, can create a shortcut the table
+/// overestimates, or join parts of the map which the table considers unreachable from each other.
+/// Once that has happened, set `diverged` so that searches use the fallback alone until the table
+/// can be recomputed. As long as the fallback is admissible, so is the combination.
+pub struct Fallback<T, H = HammingDistance> {
+    pub table: T,
+    pub fallback: H,
+    /// Whether positions which were not open when the table was computed have been opened since.
+    pub diverged: bool,
+}
+
+impl<C: Node, H: Heuristic<C>> Heuristic<C> for Fallback<AllPairsShortestPaths<C>, H> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        match self.table.distance_between(start, end) {
+            Some(distance) if !self.diverged => distance,
+            _ => self.fallback.heuristic_distance(start, end),
         }
     }
-    for position in open_positions.iter().copied() {
-        distances.insert((position, position), WithInfinity::Normal(0));
+}
+
+impl<C: Node, H: Heuristic<C>> Heuristic<C> for Fallback<Landmarks<C>, H> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        match self.table.lower_bound(start, end) {
+            Some(distance) if !self.diverged => distance,
+            _ => self.fallback.heuristic_distance(start, end),
+        }
+    }
+}
+
+/// The sentinel stored in compact distance tables for pairs of positions with no path between them.
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Clamps a distance into a [`u32`], which only ever makes it smaller and so keeps any heuristic
+/// built from it admissible.
+fn compact_distance(distance: u64) -> u32 {
+    distance.min(UNREACHABLE as u64 - 1) as u32
+}
+
+fn expand_distance(distance: u32) -> WithInfinity<u64> {
+    if distance == UNREACHABLE {
+        WithInfinity::Infinity
+    } else {
+        WithInfinity::Normal(distance as u64)
+    }
+}
+
+impl<C: Node> AllPairsShortestPaths<C> {
+    /// Returns the distance between two positions, or [`None`] if either of them was not open
+    /// when the table was computed.
+    pub fn distance_between(&self, position: C, other_position: C) -> Option<WithInfinity<u64>> {
+        let i = *self.indices.get(&position)? as usize;
+        let j = *self.indices.get(&other_position)? as usize;
+        Some(expand_distance(self.distances[i * self.indices.len() + j]))
+    }
+
+    /// The number of open positions in the table.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns whether or not the table has no open positions.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Returns whether or not the given position was open when the table was computed.
+    pub fn contains(&self, position: C) -> bool {
+        self.indices.contains_key(&position)
+    }
+}
+
+/// Assigns each open position a dense index, in the order defined on the positions.
+fn index_open_positions<C: Node, P: PassableSet<C> + ?Sized>(
+    open_positions: &P,
+) -> (Vec<C>, HashMap<C, u32>) {
+    let open_positions: Vec<C> = open_positions
+        .passable_positions()
+        .collect::<BTreeSet<C>>()
+        .into_iter()
+        .collect();
+    assert!(
+        open_positions.len() < UNREACHABLE as usize,
+        "too many open positions to index"
+    );
+    let indices = open_positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| (*position, index as u32))
+        .collect();
+    (open_positions, indices)
+}
+
+/// The open positions of a map, referred to by their dense indices, with the neighbors of each
+/// stored contiguously.
+struct IndexedGraph {
+    offsets: Vec<usize>,
+    neighbors: Vec<(u32, u64)>,
+    unit_costs: bool,
+}
+
+impl IndexedGraph {
+    fn new<C: Node, M: MovementCost<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+        open_positions: &[C],
+        indices: &HashMap<C, u32>,
+        costs: &M,
+        neighborhood: &N,
+    ) -> Self {
+        let mut offsets = Vec::with_capacity(open_positions.len() + 1);
+        let mut neighbors = Vec::new();
+        offsets.push(0);
+        for position in open_positions.iter().copied() {
+            for adjacent in neighborhood.neighbors(position) {
+                if let (Some(index), Some(cost)) = (
+                    indices.get(&adjacent),
+                    costs.movement_cost(position, adjacent),
+                ) {
+                    neighbors.push((*index, cost));
+                }
+            }
+            offsets.push(neighbors.len());
+        }
+        let unit_costs = neighbors.iter().all(|(_, cost)| *cost == 1);
+        IndexedGraph {
+            offsets,
+            neighbors,
+            unit_costs,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns the graph with every edge flipped around, so that distances from a source in the
+    /// reversed graph are distances to it in the original.
+    fn reversed(&self) -> Self {
+        let mut incoming: Vec<Vec<(u32, u64)>> = vec![Vec::new(); self.len()];
+        for index in 0..self.len() {
+            for (neighbor, cost) in self.neighbors(index) {
+                incoming[*neighbor as usize].push((index as u32, *cost));
+            }
+        }
+        let mut offsets = Vec::with_capacity(self.len() + 1);
+        offsets.push(0);
+        let mut neighbors = Vec::with_capacity(self.neighbors.len());
+        for edges in incoming {
+            neighbors.extend(edges);
+            offsets.push(neighbors.len());
+        }
+        IndexedGraph {
+            offsets,
+            neighbors,
+            unit_costs: self.unit_costs,
+        }
     }
 
-    for k in open_positions.iter().copied() {
-        for i in open_positions.iter().copied() {
-            for j in open_positions.iter().copied() {
-                let new_distance_candidate =
-                    *distances.get(&(i, k)).unwrap() + *distances.get(&(k, j)).unwrap();
-                let old_distance_candidate = *distances.get(&(i, j)).unwrap();
-                if new_distance_candidate < old_distance_candidate {
-                    distances.insert((i, j), new_distance_candidate);
-                }
+    /// Computes the full distance matrix, one source at a time.
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn all_distances(&self) -> Vec<u32> {
+        let n = self.len();
+        let mut distances = vec![UNREACHABLE; n * n];
+        if n > 0 {
+            for (source, row) in distances.chunks_mut(n).enumerate() {
+                self.distances_from(source, row);
+            }
+        }
+        distances
+    }
+
+    /// Computes the full distance matrix, distributing the sources across threads.
+    #[cfg(feature = "rayon")]
+    fn all_distances_parallel(&self) -> Vec<u32> {
+        use rayon::prelude::*;
+
+        let n = self.len();
+        let mut distances = vec![UNREACHABLE; n * n];
+        if n > 0 {
+            distances
+                .par_chunks_mut(n)
+                .enumerate()
+                .for_each(|(source, row)| self.distances_from(source, row));
+        }
+        distances
+    }
+
+    /// Computes one row of distances for each of the given sources, one after another.
+    fn distances_from_sources(&self, sources: &[usize]) -> Vec<u32> {
+        let n = self.len();
+        let mut distances = vec![UNREACHABLE; n * sources.len()];
+        if n > 0 {
+            for (source, row) in sources.iter().zip(distances.chunks_mut(n)) {
+                self.distances_from(*source, row);
+            }
+        }
+        distances
+    }
+
+    fn neighbors(&self, index: usize) -> &[(u32, u64)] {
+        &self.neighbors[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    /// Fills in the row of the distance matrix for the given source, which must start out
+    /// unreachable everywhere. When every step costs 1 this is a breadth first search, otherwise it
+    /// is Dijkstra's algorithm.
+    fn distances_from(&self, source: usize, row: &mut [u32]) {
+        if self.unit_costs {
+            let mut queue = VecDeque::new();
+            row[source] = 0;
+            queue.push_back((source, 0));
+            while let Some((index, distance)) = queue.pop_front() {
+                for (neighbor, _) in self.neighbors(index) {
+                    let neighbor = *neighbor as usize;
+                    if row[neighbor] == UNREACHABLE {
+                        row[neighbor] = compact_distance(distance + 1);
+                        queue.push_back((neighbor, distance + 1));
+                    }
+                }
+            }
+        } else {
+            let mut best = vec![u64::MAX; row.len()];
+            let mut queue = BinaryHeap::new();
+            best[source] = 0;
+            queue.push(Reverse((0, source)));
+            while let Some(Reverse((distance, index))) = queue.pop() {
+                if distance > best[index] {
+                    continue;
+                }
+                row[index] = compact_distance(distance);
+                for (neighbor, cost) in self.neighbors(index) {
+                    let neighbor = *neighbor as usize;
+                    let alt = distance.saturating_add(*cost);
+                    if alt < best[neighbor] {
+                        best[neighbor] = alt;
+                        queue.push(Reverse((alt, neighbor)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Computes a data structure caching the distances between all open positions. With the `rayon`
+/// feature enabled, the distances from each position are computed in parallel.
+pub fn all_pairs_shortest_paths<C: Coordinate, P: PassableSet<C> + ?Sized>(
+    open_positions: &P,
+) -> AllPairsShortestPaths<C> {
+    all_pairs_shortest_paths_with(open_positions, &VonNeumann)
+}
+
+/// Computes a data structure caching the distances between all open positions, moving between
+/// positions according to the given [`Neighborhood`].
+pub fn all_pairs_shortest_paths_with<
+    C: Node,
+    P: PassableSet<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    open_positions: &P,
+    neighborhood: &N,
+) -> AllPairsShortestPaths<C> {
+    all_pairs_cheapest_paths_with(open_positions, &UnitCost(open_positions), neighborhood)
+}
+
+/// Computes a data structure caching the cheapest costs of travelling between all open positions,
+/// where the cost of each step is given by the [`MovementCost`] map. The result is an admissible
+/// heuristic for [`Heuristic::find_cheapest_path`] over the same costs, under the same conditions
+/// as described on [`AllPairsShortestPaths`].
+pub fn all_pairs_cheapest_paths<
+    C: Coordinate,
+    P: PassableSet<C> + ?Sized,
+    M: MovementCost<C> + ?Sized,
+>(
+    open_positions: &P,
+    costs: &M,
+) -> AllPairsShortestPaths<C> {
+    all_pairs_cheapest_paths_with(open_positions, costs, &VonNeumann)
+}
+
+/// Computes the cheapest costs of travelling between all open positions as in
+/// [`all_pairs_cheapest_paths`], moving between positions according to the given
+/// [`Neighborhood`].
+pub fn all_pairs_cheapest_paths_with<
+    C: Node,
+    P: PassableSet<C> + ?Sized,
+    M: MovementCost<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    open_positions: &P,
+    costs: &M,
+    neighborhood: &N,
+) -> AllPairsShortestPaths<C> {
+    let (open_positions, indices) = index_open_positions(open_positions);
+    let graph = IndexedGraph::new(&open_positions, &indices, costs, neighborhood);
+    #[cfg(feature = "rayon")]
+    let distances = graph.all_distances_parallel();
+    #[cfg(not(feature = "rayon"))]
+    let distances = graph.all_distances();
+
+    AllPairsShortestPaths { indices, distances }
+}
+
+/// The landmark, or ALT, heuristic trades some of the precision of [`AllPairsShortestPaths`] for a
+/// table which grows linearly with the size of the map. A handful of landmarks are chosen on the
+/// static map, and the distances between each of them and every open position are stored. By the
+/// triangle inequality, for any landmark `L` the distance from `s` to `t` is at least
+/// `d(L, t) - d(L, s)` and at least `d(s, L) - d(t, L)`, and the heuristic is the best of these
+/// bounds.
+///
+/// Landmarks work best when they sit behind the positions being searched between, out at the
+/// edges of the map, which is what [`farthest_point_landmarks`] aims for.
+///
+/// **Correctness**: Distances on the static map are lower bounds for distances on any dynamic map
+/// whose open positions are a subset of it, so the same argument as for [`AllPairsShortestPaths`]
+/// applies, and the same care should be taken that the dynamically open positions are a subset of
+/// the statically open positions.
+///
+/// **Memory**: Two [`u32`] distances are stored per landmark per open position, one in each
+/// direction, so that the bounds remain admissible even when movement costs depend on direction.
+pub struct Landmarks<C = Position> {
+    landmarks: Vec<C>,
+    indices: HashMap<C, u32>,
+    from_landmarks: Vec<u32>,
+    to_landmarks: Vec<u32>,
+}
+
+impl<C: Node> Heuristic<C> for Landmarks<C> {
+    fn heuristic_distance(&self, start: C, end: C) -> WithInfinity<u64> {
+        self.lower_bound(start, end).unwrap_or_default()
+    }
+}
+
+impl<C: Node> Landmarks<C> {
+    /// Computes the distances between the given landmarks and every open position, where the cost
+    /// of each step is given by the [`MovementCost`] map. Landmarks which are not open are ignored.
+    pub fn new<
+        P: PassableSet<C> + ?Sized,
+        M: MovementCost<C> + ?Sized,
+        N: Neighborhood<C> + ?Sized,
+    >(
+        open_positions: &P,
+        costs: &M,
+        neighborhood: &N,
+        landmarks: impl IntoIterator<Item = C>,
+    ) -> Self {
+        let (open_positions, indices) = index_open_positions(open_positions);
+        let graph = IndexedGraph::new(&open_positions, &indices, costs, neighborhood);
+        let sources: Vec<usize> = landmarks
+            .into_iter()
+            .filter_map(|landmark| indices.get(&landmark).map(|index| *index as usize))
+            .collect();
+        Landmarks::from_sources(&open_positions, indices, &graph, &sources)
+    }
+
+    fn from_sources(
+        open_positions: &[C],
+        indices: HashMap<C, u32>,
+        graph: &IndexedGraph,
+        sources: &[usize],
+    ) -> Self {
+        Landmarks {
+            landmarks: sources
+                .iter()
+                .map(|source| open_positions[*source])
+                .collect(),
+            indices,
+            from_landmarks: graph.distances_from_sources(sources),
+            to_landmarks: graph.reversed().distances_from_sources(sources),
+        }
+    }
+
+    /// Returns a lower bound on the distance between two positions, or [`None`] if either of them
+    /// was not open when the table was computed. The bound is only infinite when there is
+    /// certainly no path between them.
+    pub fn lower_bound(&self, position: C, other_position: C) -> Option<WithInfinity<u64>> {
+        let s = *self.indices.get(&position)? as usize;
+        let t = *self.indices.get(&other_position)? as usize;
+        let n = self.indices.len();
+        let mut bound = 0;
+        for (from, to) in self
+            .from_landmarks
+            .chunks(n)
+            .zip(self.to_landmarks.chunks(n))
+        {
+            if from[s] != UNREACHABLE {
+                if from[t] == UNREACHABLE {
+                    return Some(WithInfinity::Infinity);
+                }
+                bound = bound.max(from[t].saturating_sub(from[s]));
+            }
+            if to[t] != UNREACHABLE {
+                if to[s] == UNREACHABLE {
+                    return Some(WithInfinity::Infinity);
+                }
+                bound = bound.max(to[s].saturating_sub(to[t]));
+            }
+        }
+        Some(WithInfinity::Normal(bound as u64))
+    }
+
+    /// The chosen landmarks, in the order they were selected.
+    pub fn landmarks(&self) -> &[C] {
+        &self.landmarks
+    }
+
+    /// The number of open positions in the table.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns whether or not the table has no open positions.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Returns whether or not the given position was open when the table was computed.
+    pub fn contains(&self, position: C) -> bool {
+        self.indices.contains_key(&position)
+    }
+}
+
+/// Chooses `count` landmarks spread out over the open positions and computes the [`Landmarks`]
+/// heuristic from them.
+pub fn farthest_point_landmarks<C: Coordinate, P: PassableSet<C> + ?Sized>(
+    open_positions: &P,
+    count: usize,
+) -> Landmarks<C> {
+    farthest_point_landmarks_with(
+        open_positions,
+        &UnitCost(open_positions),
+        &VonNeumann,
+        count,
+    )
+}
+
+/// Chooses `count` landmarks by farthest point selection: the first is the open position farthest
+/// from the smallest one, and each one after that is the open position farthest from all of the
+/// landmarks chosen so far. Positions unreachable from every landmark so far are preferred, so
+/// that each disconnected region of the map gets a landmark of its own.
+pub fn farthest_point_landmarks_with<
+    C: Node,
+    P: PassableSet<C> + ?Sized,
+    M: MovementCost<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+>(
+    open_positions: &P,
+    costs: &M,
+    neighborhood: &N,
+    count: usize,
+) -> Landmarks<C> {
+    let (open_positions, indices) = index_open_positions(open_positions);
+    let graph = IndexedGraph::new(&open_positions, &indices, costs, neighborhood);
+    let n = graph.len();
+    let mut sources = Vec::with_capacity(count.min(n));
+    if n > 0 && count > 0 {
+        let mut nearest = vec![UNREACHABLE; n];
+        graph.distances_from(0, &mut nearest);
+        let first = (0..n)
+            .filter(|index| nearest[*index] != UNREACHABLE)
+            .max_by_key(|index| (nearest[*index], Reverse(*index)))
+            .unwrap_or(0);
+        nearest.fill(UNREACHABLE);
+        let mut next = Some(first);
+        while let Some(source) = next {
+            sources.push(source);
+            if sources.len() == count {
+                break;
+            }
+            let mut row = vec![UNREACHABLE; n];
+            graph.distances_from(source, &mut row);
+            for (nearest, distance) in nearest.iter_mut().zip(row) {
+                *nearest = (*nearest).min(distance);
             }
+            next = (0..n)
+                .filter(|index| nearest[*index] != 0)
+                .max_by_key(|index| (nearest[*index], Reverse(*index)));
         }
     }
+    Landmarks::from_sources(&open_positions, indices, &graph, &sources)
+}
+
+/// Chooses `count` distinct landmarks uniformly at random from the open positions and computes
+/// the [`Landmarks`] heuristic from them.
+#[cfg(feature = "rand")]
+pub fn random_landmarks<C: Coordinate, P: PassableSet<C> + ?Sized, R: rand::Rng + ?Sized>(
+    open_positions: &P,
+    count: usize,
+    rng: &mut R,
+) -> Landmarks<C> {
+    random_landmarks_with(
+        open_positions,
+        &UnitCost(open_positions),
+        &VonNeumann,
+        count,
+        rng,
+    )
+}
 
-    AllPairsShortestPaths(distances)
+/// Chooses `count` landmarks at random as in [`random_landmarks`], computing distances from them
+/// with the given [`MovementCost`] map and [`Neighborhood`].
+#[cfg(feature = "rand")]
+pub fn random_landmarks_with<
+    C: Node,
+    P: PassableSet<C> + ?Sized,
+    M: MovementCost<C> + ?Sized,
+    N: Neighborhood<C> + ?Sized,
+    R: rand::Rng + ?Sized,
+>(
+    open_positions: &P,
+    costs: &M,
+    neighborhood: &N,
+    count: usize,
+    rng: &mut R,
+) -> Landmarks<C> {
+    let (open_positions, indices) = index_open_positions(open_positions);
+    let graph = IndexedGraph::new(&open_positions, &indices, costs, neighborhood);
+    let sources = rand::seq::index::sample(rng, graph.len(), count.min(graph.len())).into_vec();
+    Landmarks::from_sources(&open_positions, indices, &graph, &sources)
 }
 
 #[test]
@@ -362,3 +1392,777 @@ fn shortest_path_test() {
         }
     }
 }
+
+#[test]
+fn passable_shortest_path_test() {
+    use std::collections::HashSet;
+
+    let corridor = |position: Position| {
+        (0..10).contains(&position.x) && position.x != 3 && position.y == 0 && position.z == 0
+    };
+    let hashed: HashSet<Position> = (0..10)
+        .filter(|x| *x != 3)
+        .map(|x| Position::new(x, 0, 0))
+        .collect();
+
+    for open_positions in [&corridor as &dyn Passable, &hashed as &dyn Passable] {
+        assert_eq!(
+            HammingDistance
+                .find_shortest_path(
+                    open_positions,
+                    Position::new(0, 0, 0),
+                    Position::new(2, 0, 0)
+                )
+                .map(|path| path.len()),
+            Some(2)
+        );
+        assert_eq!(
+            HammingDistance.find_shortest_path(
+                open_positions,
+                Position::new(0, 0, 0),
+                Position::new(5, 0, 0)
+            ),
+            None
+        );
+    }
+
+    let all_pairs = all_pairs_shortest_paths(&hashed);
+    assert_eq!(
+        all_pairs.distance_between(Position::new(4, 0, 0), Position::new(9, 0, 0)),
+        Some(WithInfinity::Normal(5))
+    );
+}
+
+#[test]
+fn cheapest_path_test() {
+    use std::collections::BTreeMap;
+
+    // A 5x3 field where the middle row is mud, apart from a road at x = 4.
+    let mut costs: BTreeMap<Position, u64> = BTreeMap::new();
+    for x in 0..5 {
+        for y in 0..3 {
+            let cost = if y == 1 && x != 4 { 10 } else { 2 };
+            costs.insert(Position::new(x, y, 0), cost);
+        }
+    }
+
+    let start = Position::new(0, 0, 0);
+    let end = Position::new(0, 2, 0);
+    let heuristic = WeightedHammingDistance { minimum_cost: 2 };
+
+    let (path, cost) = heuristic.find_cheapest_path(&costs, start, end).unwrap();
+    assert_eq!(cost, 12);
+    assert_eq!(path.len(), 2);
+
+    for x in 0..4 {
+        costs.insert(Position::new(x, 1, 0), 100);
+    }
+    let (path, cost) = heuristic.find_cheapest_path(&costs, start, end).unwrap();
+    assert_eq!(cost, 2 * 10);
+    assert!(path.contains(&Position::new(4, 1, 0)));
+
+    let open_positions: BTreeSet<Position> = costs.keys().copied().collect();
+    let all_pairs = all_pairs_cheapest_paths(&open_positions, &costs);
+    assert_eq!(
+        all_pairs.distance_between(start, end),
+        Some(WithInfinity::Normal(cost))
+    );
+    assert_eq!(
+        all_pairs.find_cheapest_path(&costs, start, end),
+        Some((path, cost))
+    );
+    assert_eq!(
+        HammingDistance
+            .find_shortest_path(&open_positions, start, end)
+            .map(|path| path.len()),
+        Some(2)
+    );
+}
+
+#[test]
+fn neighborhood_shortest_path_test() {
+    use crate::{
+        neighborhood::{Moore, PlanarMoore},
+        test_util::assert_valid_path,
+    };
+    use itertools::Itertools;
+
+    const N: i64 = 6;
+
+    // An open plane with a wall along x = 3 which has a single gap at y = 0.
+    let open_positions: BTreeSet<Position> = (0..N)
+        .cartesian_product(0..N)
+        .filter(|(x, y)| *x != 3 || *y == 0)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let start = Position::new(0, N - 1, 0);
+    let end = Position::new(N - 1, N - 1, 0);
+
+    assert_eq!(
+        HammingDistance
+            .find_shortest_path(&open_positions, start, end)
+            .map(|path| path.len()),
+        Some(3 * (N - 1) as usize)
+    );
+
+    let diagonal = ChebyshevDistance
+        .find_shortest_path_with(&open_positions, start, end, &PlanarMoore)
+        .unwrap();
+    assert_eq!(diagonal.len(), 2 * (N - 1) as usize);
+    assert_valid_path(&PlanarMoore, &open_positions, start, diagonal, end);
+
+    let all_pairs = all_pairs_shortest_paths_with(&open_positions, &Moore);
+    assert_eq!(
+        all_pairs.distance_between(start, end),
+        Some(WithInfinity::Normal(2 * (N - 1) as u64))
+    );
+
+    // Diagonal steps cost 3 and straight steps cost 2.
+    let costs = |from: Position, to: Position| {
+        if !open_positions.contains(&to) {
+            None
+        } else if from.hamming_distance(to) == 1 {
+            Some(2)
+        } else {
+            Some(3)
+        }
+    };
+    let octile = OctileDistance {
+        straight: 2,
+        diagonal: 3,
+    };
+    let (_, cost) = octile
+        .find_cheapest_path_with(&costs, start, end, &PlanarMoore)
+        .unwrap();
+    assert_eq!(
+        Some(WithInfinity::Normal(cost)),
+        all_pairs_cheapest_paths_with(&open_positions, &costs, &PlanarMoore)
+            .distance_between(start, end)
+    );
+    // Five diagonal and five straight steps through the gap.
+    assert_eq!(cost, 5 * 3 + 5 * 2);
+
+    // Diagonal steps may also change z, which the heuristic must not charge for separately.
+    let cube: BTreeSet<Position> = (0..4)
+        .cartesian_product(0..4)
+        .cartesian_product(0..4)
+        .map(|((x, y), z)| Position::new(x, y, z))
+        .collect();
+    let costs = |from: Position, to: Position| {
+        if !cube.contains(&to) {
+            None
+        } else if from.hamming_distance(to) == 1 {
+            Some(2)
+        } else {
+            Some(3)
+        }
+    };
+    let all_pairs = all_pairs_cheapest_paths_with(&cube, &costs, &Moore);
+    let corner = Position::new(0, 0, 0);
+    for position in cube.iter().copied() {
+        let distance = all_pairs.distance_between(corner, position).unwrap();
+        assert!(octile.heuristic_distance(corner, position) <= distance);
+    }
+    assert_eq!(
+        octile.heuristic_distance(corner, Position::new(3, 0, 3)),
+        WithInfinity::Normal(9)
+    );
+}
+
+#[test]
+fn position2_shortest_path_test() {
+    use crate::neighborhood::Moore;
+    use itertools::Itertools;
+
+    const N: i64 = 5;
+
+    let static_open_positions: BTreeSet<Position2> = (0..N)
+        .cartesian_product(0..N)
+        .filter(|(x, y)| *x != 2 || *y == 0)
+        .map(|(x, y)| Position2::new(x, y))
+        .collect();
+    let start = Position2::new(0, N - 1);
+    let end = Position2::new(N - 1, N - 1);
+
+    let all_pairs = all_pairs_shortest_paths(&static_open_positions);
+    let path = all_pairs
+        .find_shortest_path(&static_open_positions, start, end)
+        .unwrap();
+    assert_eq!(path.len() as u64, 3 * (N - 1) as u64);
+    assert_eq!(
+        HammingDistance.find_shortest_path(&static_open_positions, start, end),
+        Some(path)
+    );
+
+    assert_eq!(
+        ChebyshevDistance
+            .find_shortest_path_with(&static_open_positions, start, end, &Moore)
+            .map(|path| path.len() as u64),
+        Some(2 * (N - 1) as u64)
+    );
+}
+
+#[test]
+fn hex_shortest_path_test() {
+    use crate::bfs::Bfs;
+
+    let center = Hex::new(0, 0);
+    // A hexagonal map of radius 4 with a wall along the ring of radius 2, which only has a gap
+    // on its far side.
+    let gap = Hex::new(-2, 0);
+    let open_positions: BTreeSet<Hex> = center
+        .spiral(4)
+        .filter(|hex| hex.hex_distance(center) != 2 || *hex == gap)
+        .collect();
+
+    for (hex, distance) in Bfs::new(&open_positions, center) {
+        if hex.hex_distance(center) < 2 {
+            assert_eq!(distance, hex.hex_distance(center));
+        }
+    }
+
+    let end = Hex::new(3, 0);
+    let path = HexDistance
+        .find_shortest_path(&open_positions, center, end)
+        .unwrap();
+    assert!(path.contains(&gap));
+    assert_eq!(
+        Some(WithInfinity::Normal(path.len() as u64)),
+        all_pairs_shortest_paths(&open_positions).distance_between(center, end)
+    );
+    assert_eq!(
+        Bfs::new(&open_positions, center).find(|(hex, _)| *hex == end),
+        Some((end, path.len() as u64))
+    );
+}
+
+#[test]
+fn graph_shortest_path_test() {
+    use std::collections::HashMap;
+
+    use crate::{bfs::Bfs, test_util::assert_valid_path};
+
+    /// A waypoint in a navigation graph which is not laid out on any grid.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Waypoint(u8);
+
+    let mut graph: HashMap<Waypoint, Vec<Waypoint>> = HashMap::new();
+    for (a, b) in [(0, 1), (1, 2), (2, 3), (0, 4), (4, 3), (3, 5)] {
+        graph.entry(Waypoint(a)).or_default().push(Waypoint(b));
+        graph.entry(Waypoint(b)).or_default().push(Waypoint(a));
+    }
+    let open_positions: BTreeSet<Waypoint> = graph.keys().copied().collect();
+
+    let path = Dijkstra
+        .find_shortest_path_with(&open_positions, Waypoint(0), Waypoint(5), &graph)
+        .unwrap();
+    assert_eq!(
+        path,
+        vec![Waypoint(4), Waypoint(3), Waypoint(5)]
+            .into_iter()
+            .collect::<VecDeque<_>>()
+    );
+
+    let all_pairs = all_pairs_shortest_paths_with(&open_positions, &graph);
+    for (waypoint, distance) in Bfs::with_neighborhood(&open_positions, Waypoint(0), &graph) {
+        assert_eq!(
+            all_pairs.distance_between(Waypoint(0), waypoint),
+            Some(WithInfinity::Normal(distance))
+        );
+    }
+
+    let without_four = |waypoint: Waypoint| waypoint != Waypoint(4);
+    let detour = all_pairs
+        .find_shortest_path_with(&without_four, Waypoint(0), Waypoint(5), &graph)
+        .unwrap();
+    assert_eq!(detour.len(), 4);
+    assert_valid_path(&graph, &without_four, Waypoint(0), detour, Waypoint(5));
+
+    let start = Position::new(0, 0, 0);
+    let end = Position::new(3, -2, 1);
+    let open = |position: Position| position.hamming_distance(start) < 10;
+    assert_eq!(
+        CoordinateDistance
+            .find_shortest_path(&open, start, end)
+            .map(|path| path.len() as u64),
+        Some(start.hamming_distance(end))
+    );
+}
+
+#[test]
+fn compact_all_pairs_test() {
+    use itertools::Itertools;
+
+    let open_positions: BTreeSet<Position> = (0..4)
+        .cartesian_product(0..4)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .chain(std::iter::once(Position::new(10, 10, 10)))
+        .collect();
+    let all_pairs = all_pairs_shortest_paths(&open_positions);
+
+    assert_eq!(all_pairs.len(), open_positions.len());
+    assert!(all_pairs.contains(Position::new(10, 10, 10)));
+    assert!(!all_pairs.contains(Position::new(4, 4, 0)));
+    for position in open_positions.iter().copied() {
+        for other in open_positions.iter().copied() {
+            let expected = if position.z == other.z {
+                WithInfinity::Normal(position.hamming_distance(other))
+            } else {
+                WithInfinity::Infinity
+            };
+            assert_eq!(all_pairs.distance_between(position, other), Some(expected));
+        }
+    }
+    assert_eq!(
+        all_pairs.distance_between(Position::new(0, 0, 0), Position::new(4, 4, 0)),
+        None
+    );
+
+    // Distances which do not fit are clamped rather than wrapping around or becoming infinite.
+    let costs = |_from: Position, _to: Position| Some(u32::MAX as u64);
+    let expensive = all_pairs_cheapest_paths(&open_positions, &costs);
+    assert_eq!(
+        expensive.distance_between(Position::new(0, 0, 0), Position::new(3, 3, 0)),
+        Some(WithInfinity::Normal(u32::MAX as u64 - 1))
+    );
+}
+
+#[test]
+fn all_pairs_matches_search_test() {
+    use crate::bfs::Bfs;
+    use itertools::Itertools;
+
+    const N: i64 = 8;
+
+    // A maze-like map with some walls and an unreachable pocket in the corner.
+    let open_positions: BTreeSet<Position> = (0..N)
+        .cartesian_product(0..N)
+        .filter(|(x, y)| !((x % 3 == 1 && y % 4 != 0) || (*x == N - 2 && *y == N - 1)))
+        .filter(|(x, y)| !(*x == N - 1 && *y == N - 2))
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+
+    let all_pairs = all_pairs_shortest_paths(&open_positions);
+    for source in open_positions.iter().copied() {
+        let reached: BTreeMap<Position, u64> = Bfs::new(&open_positions, source).collect();
+        for target in open_positions.iter().copied() {
+            assert_eq!(
+                all_pairs.distance_between(source, target),
+                Some(
+                    reached
+                        .get(&target)
+                        .map_or(WithInfinity::Infinity, |d| WithInfinity::Normal(*d))
+                )
+            );
+        }
+    }
+
+    let costs = |_from: Position, to: Position| {
+        if open_positions.contains(&to) {
+            Some(1 + (to.x * to.y % 5) as u64)
+        } else {
+            None
+        }
+    };
+    let weighted = all_pairs_cheapest_paths(&open_positions, &costs);
+    for source in open_positions.iter().copied().step_by(3) {
+        for target in open_positions.iter().copied() {
+            assert_eq!(
+                weighted.distance_between(source, target),
+                Some(
+                    Dijkstra
+                        .find_cheapest_path(&costs, source, target)
+                        .map_or(WithInfinity::Infinity, |(_, cost)| WithInfinity::Normal(
+                            cost
+                        ))
+                )
+            );
+        }
+    }
+}
+
+#[test]
+fn landmarks_test() {
+    use itertools::Itertools;
+
+    const N: i64 = 8;
+
+    let square: BTreeSet<Position> = (0..N)
+        .cartesian_product(0..N)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let corners = farthest_point_landmarks(&square, 3);
+    assert_eq!(
+        corners.landmarks(),
+        &[
+            Position::new(N - 1, N - 1, 0),
+            Position::new(0, 0, 0),
+            Position::new(0, N - 1, 0),
+        ]
+    );
+    assert_eq!(farthest_point_landmarks(&square, 100).landmarks().len(), 64);
+
+    // The same maze as above, with walls and an unreachable pocket in the corner.
+    let open_positions: BTreeSet<Position> = (0..N)
+        .cartesian_product(0..N)
+        .filter(|(x, y)| !((x % 3 == 1 && y % 4 != 0) || (*x == N - 2 && *y == N - 1)))
+        .filter(|(x, y)| !(*x == N - 1 && *y == N - 2))
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let uphill = |from: Position, to: Position| {
+        if open_positions.contains(&to) {
+            Some(if to.x > from.x { 3 } else { 1 })
+        } else {
+            None
+        }
+    };
+
+    for (all_pairs, landmarks) in [
+        (
+            all_pairs_shortest_paths(&open_positions),
+            farthest_point_landmarks(&open_positions, 3),
+        ),
+        (
+            all_pairs_cheapest_paths(&open_positions, &uphill),
+            farthest_point_landmarks_with(&open_positions, &uphill, &VonNeumann, 3),
+        ),
+    ] {
+        assert_eq!(landmarks.landmarks().len(), 3);
+        for source in open_positions.iter().copied() {
+            for target in open_positions.iter().copied() {
+                let bound = landmarks.lower_bound(source, target).unwrap();
+                let distance = all_pairs.distance_between(source, target).unwrap();
+                assert!(bound <= distance);
+                if landmarks.landmarks().contains(&source) {
+                    assert_eq!(bound, distance);
+                }
+            }
+        }
+    }
+    let mut split = open_positions.clone();
+    split.insert(Position::new(0, 0, 5));
+    let split_landmarks = farthest_point_landmarks(&split, 2);
+    assert!(split_landmarks
+        .landmarks()
+        .contains(&Position::new(0, 0, 5)));
+    assert_eq!(
+        split_landmarks.lower_bound(Position::new(0, 0, 0), Position::new(0, 0, 5)),
+        Some(WithInfinity::Infinity)
+    );
+
+    // Searching with landmarks on a dynamic subset of the static map stays optimal.
+    let landmarks = farthest_point_landmarks(&open_positions, 4);
+    let mut dynamic = open_positions.clone();
+    dynamic.remove(&Position::new(3, 4, 0));
+    dynamic.remove(&Position::new(6, 0, 0));
+    for target in dynamic.iter().copied() {
+        assert_eq!(
+            landmarks
+                .find_shortest_path(&dynamic, Position::new(0, 0, 0), target)
+                .map(|path| path.len()),
+            HammingDistance
+                .find_shortest_path(&dynamic, Position::new(0, 0, 0), target)
+                .map(|path| path.len())
+        );
+    }
+    assert_eq!(
+        landmarks.heuristic_distance(Position::new(0, 0, 0), Position::new(0, 0, 1)),
+        WithInfinity::Infinity
+    );
+
+    let explicit = Landmarks::new(
+        &open_positions,
+        &UnitCost(&open_positions),
+        &VonNeumann,
+        [Position::new(0, 0, 0), Position::new(1, 1, 0)],
+    );
+    assert_eq!(explicit.landmarks(), &[Position::new(0, 0, 0)]);
+}
+
+#[test]
+fn bidirectional_test() {
+    use itertools::Itertools;
+
+    use crate::test_util::assert_valid_path;
+
+    let open_positions: BTreeSet<Position> = (0..12)
+        .cartesian_product(0..12)
+        .filter(|(x, y)| !((x % 4 == 2 && y % 6 != 1) || (y % 5 == 3 && x % 7 != 0)))
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let costs = |from: Position, to: Position| {
+        if open_positions.contains(&to) {
+            Some(if to.x > from.x {
+                4
+            } else {
+                1 + (to.y % 2) as u64
+            })
+        } else {
+            None
+        }
+    };
+
+    for start in open_positions.iter().copied().step_by(23) {
+        for end in open_positions.iter().copied() {
+            let expected = HammingDistance.find_shortest_path(&open_positions, start, end);
+            for found in [
+                HammingDistance.find_shortest_path_bidirectional(&open_positions, start, end),
+                Dijkstra.find_shortest_path_bidirectional(&open_positions, start, end),
+            ] {
+                assert_eq!(
+                    found.as_ref().map(|path| path.len()),
+                    expected.as_ref().map(|path| path.len())
+                );
+                if let Some(path) = found {
+                    assert_valid_path(&VonNeumann, &open_positions, start, path, end);
+                }
+            }
+
+            let expected = Dijkstra.find_cheapest_path(&costs, start, end);
+            let found = Dijkstra.find_cheapest_path_bidirectional(&costs, start, end);
+            assert_eq!(
+                found.as_ref().map(|(_, cost)| *cost),
+                expected.map(|(_, cost)| cost)
+            );
+            if let Some((path, cost)) = found {
+                let mut previous = start;
+                let mut total = 0;
+                for position in path {
+                    total += costs(previous, position).unwrap();
+                    previous = position;
+                }
+                assert_eq!((previous, total), (end, cost));
+            }
+        }
+    }
+}
+
+#[test]
+fn combinator_test() {
+    use itertools::Itertools;
+
+    // A wall across the map with a gap at the far end, so that Hamming distances are too low.
+    let open_positions: BTreeSet<Position> = (0..10)
+        .cartesian_product(0..10)
+        .filter(|(x, y)| *x != 5 || *y == 9)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let all_pairs = all_pairs_shortest_paths(&open_positions);
+    let (start, end) = (Position::new(0, 0, 0), Position::new(9, 0, 0));
+
+    let max = Max(&all_pairs, HammingDistance);
+    assert_eq!(max.heuristic_distance(start, end), WithInfinity::Normal(27));
+    assert_eq!(
+        Max(Dijkstra, HammingDistance).heuristic_distance(start, end),
+        WithInfinity::Normal(9)
+    );
+    assert_eq!(
+        max.heuristic_distance(start, Position::new(5, 0, 0)),
+        WithInfinity::Infinity
+    );
+
+    let scaled = Scaled {
+        heuristic: HammingDistance,
+        numerator: 3,
+        denominator: 2,
+    };
+    assert_eq!(
+        scaled.heuristic_distance(start, end),
+        WithInfinity::Normal(13)
+    );
+    // Scaling is exact even for distances too large to be represented exactly as a float.
+    let far = Position::new((1 << 60) + 1, 0, 0);
+    assert_eq!(
+        scaled.heuristic_distance(Position::new(0, 0, 0), far),
+        WithInfinity::Normal(3 << 59 | 1)
+    );
+    let optimal = HammingDistance
+        .find_shortest_path(&open_positions, start, end)
+        .unwrap();
+    let weighted = scaled
+        .find_shortest_path(&open_positions, start, end)
+        .unwrap();
+    assert_eq!(optimal.len(), 27);
+    assert!(weighted.len() as f64 <= 1.5 * optimal.len() as f64);
+
+    // Digging out a shortcut makes the table overestimate, so the search misses the shortcut
+    // until the fallback takes over.
+    let mut dug_out = open_positions.clone();
+    dug_out.insert(Position::new(5, 0, 0));
+    assert_eq!(
+        all_pairs
+            .find_shortest_path(&dug_out, start, end)
+            .map(|path| path.len()),
+        Some(27)
+    );
+    let mut fallback = Fallback {
+        table: all_pairs,
+        fallback: HammingDistance,
+        diverged: false,
+    };
+    assert_eq!(
+        fallback.heuristic_distance(start, Position::new(5, 0, 0)),
+        WithInfinity::Normal(5)
+    );
+    assert_eq!(
+        fallback.heuristic_distance(start, end),
+        WithInfinity::Normal(27)
+    );
+    fallback.diverged = true;
+    assert_eq!(
+        fallback.heuristic_distance(start, end),
+        WithInfinity::Normal(9)
+    );
+    assert_eq!(
+        fallback
+            .find_shortest_path(&dug_out, start, end)
+            .map(|path| path.len()),
+        Some(9)
+    );
+    let landmarks = Fallback {
+        table: farthest_point_landmarks(&open_positions, 2),
+        fallback: HammingDistance,
+        diverged: true,
+    };
+    assert_eq!(
+        landmarks
+            .find_shortest_path(&dug_out, start, end)
+            .map(|path| path.len()),
+        Some(9)
+    );
+
+    // Digging out a tile between two parts of the map which the table considers disconnected.
+    let separated: BTreeSet<Position> = [0, 1, 3, 4]
+        .into_iter()
+        .map(|x| Position::new(x, 0, 0))
+        .collect();
+    let mut joined = separated.clone();
+    joined.insert(Position::new(2, 0, 0));
+    let (start, end) = (Position::new(0, 0, 0), Position::new(4, 0, 0));
+    let mut fallback = Fallback {
+        table: all_pairs_shortest_paths(&separated),
+        fallback: HammingDistance,
+        diverged: false,
+    };
+    assert_eq!(fallback.find_shortest_path(&joined, start, end), None);
+    fallback.diverged = true;
+    assert_eq!(
+        fallback
+            .find_shortest_path(&joined, start, end)
+            .map(|path| path.len()),
+        Some(4)
+    );
+}
+
+#[test]
+fn path_result_test() {
+    use itertools::Itertools;
+
+    // The same wall as above, along with a position walled in on its own.
+    let open_positions: BTreeSet<Position> = (0..10)
+        .cartesian_product(0..10)
+        .filter(|(x, y)| *x != 5 || *y == 9)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .chain(std::iter::once(Position::new(20, 0, 0)))
+        .collect();
+    let all_pairs = all_pairs_shortest_paths(&open_positions);
+    let (start, end) = (Position::new(0, 0, 0), Position::new(9, 0, 0));
+
+    let hamming = HammingDistance
+        .try_find_shortest_path(&open_positions, start, end)
+        .unwrap();
+    let table = all_pairs
+        .try_find_shortest_path(&open_positions, start, end)
+        .unwrap();
+    assert_eq!(hamming.cost, 27);
+    assert_eq!(hamming.path.len(), 27);
+    assert_eq!(table.cost, 27);
+
+    // In an open room only the positions along the straight line between them lie on a shortest
+    // path, while Dijkstra's algorithm searches everything as close to the start as the end is.
+    let room: BTreeSet<Position> = (0..10)
+        .cartesian_product(0..10)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let guided = HammingDistance
+        .try_find_shortest_path(&room, start, end)
+        .unwrap();
+    let unguided = Dijkstra.try_find_shortest_path(&room, start, end).unwrap();
+    assert_eq!(guided.cost, unguided.cost);
+    assert_eq!(guided.stats.nodes_expanded, 9);
+    assert!(unguided.stats.nodes_expanded > 45);
+    assert!(hamming.stats.max_frontier >= 1);
+
+    assert_eq!(
+        HammingDistance.try_find_shortest_path(&open_positions, start, Position::new(5, 0, 0)),
+        Err(PathError::EndBlocked)
+    );
+    assert_eq!(
+        all_pairs.try_find_shortest_path(&open_positions, start, Position::new(20, 0, 0)),
+        Err(PathError::HeuristicUnreachable)
+    );
+    match HammingDistance.try_find_shortest_path(&open_positions, start, Position::new(20, 0, 0)) {
+        Err(PathError::Exhausted(stats)) => assert_eq!(stats.nodes_expanded, 91),
+        other => panic!("expected the search to be exhausted, found {:?}", other),
+    }
+
+    let costs = |_from: Position, to: Position| open_positions.contains(&to).then_some(2);
+    let cheapest = WeightedHammingDistance { minimum_cost: 2 }
+        .try_find_cheapest_path(&costs, start, end)
+        .unwrap();
+    assert_eq!(cheapest.cost, 54);
+    assert_eq!(cheapest.path, hamming.path);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn random_landmarks_test() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let open_positions: BTreeSet<Position2> = (0..6)
+        .flat_map(|x| (0..6).map(move |y| Position2::new(x, y)))
+        .filter(|position| position.x != 3 || position.y == 5)
+        .collect();
+    let all_pairs = all_pairs_shortest_paths(&open_positions);
+    let landmarks = random_landmarks(&open_positions, 3, &mut StdRng::seed_from_u64(7));
+    assert_eq!(landmarks.landmarks().len(), 3);
+    for source in open_positions.iter().copied() {
+        for target in open_positions.iter().copied() {
+            assert!(
+                landmarks.lower_bound(source, target) <= all_pairs.distance_between(source, target)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_all_pairs_test() {
+    use itertools::Itertools;
+
+    let open_positions: Vec<Position> = (0..12)
+        .cartesian_product(0..12)
+        .filter(|(x, y)| (x * 7 + y * 3) % 5 != 0)
+        .map(|(x, y)| Position::new(x, y, 0))
+        .collect();
+    let indices: HashMap<Position, u32> = open_positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| (*position, index as u32))
+        .collect();
+    let open: BTreeSet<Position> = open_positions.iter().copied().collect();
+    let costs = |_from: Position, to: Position| {
+        if open.contains(&to) {
+            Some(1 + (to.x + to.y) as u64 % 3)
+        } else {
+            None
+        }
+    };
+
+    for graph in [
+        IndexedGraph::new(&open_positions, &indices, &UnitCost(&open), &VonNeumann),
+        IndexedGraph::new(&open_positions, &indices, &costs, &VonNeumann),
+    ] {
+        assert_eq!(graph.all_distances_parallel(), graph.all_distances());
+    }
+}
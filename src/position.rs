@@ -5,6 +5,8 @@
 
 use std::ops::{Add, Mul, Sub};
 
+use crate::{Coordinate, Position2};
+
 /// Represents a position in a 3D grid, bounded in each dimension by the maximum size of an [`i64`].
 /// Around the bounds, all arithmetic in this module with be done in a wrapping way, so be aware
 /// of that in your usage of this module.
@@ -64,6 +66,34 @@ impl Position {
     pub fn hamming_distance(self, other: Position) -> u64 {
         self.x.abs_diff(other.x) + self.y.abs_diff(other.y) + self.z.abs_diff(other.z)
     }
+
+    /// Drops the z coordinate, projecting this position onto the xy plane.
+    pub fn truncate(self) -> Position2 {
+        Position2::new(self.x, self.y)
+    }
+
+    /// Computes the Chebyshev distance between two points, which is the largest difference along
+    /// any single axis.
+    pub fn chebyshev_distance(self, other: Position) -> u64 {
+        self.x
+            .abs_diff(other.x)
+            .max(self.y.abs_diff(other.y))
+            .max(self.z.abs_diff(other.z))
+    }
+}
+
+impl Coordinate for Position {
+    fn adjacent(self) -> Box<dyn Iterator<Item = Self>> {
+        Position::adjacent(self)
+    }
+
+    fn distance(self, other: Self) -> u64 {
+        self.hamming_distance(other)
+    }
+
+    fn is_adjacent_to(self, other: Self) -> bool {
+        Position::is_adjacent_to(self, other)
+    }
 }
 
 #[test]
@@ -0,0 +1,160 @@
+//! # Position2
+//!
+//! A data type representing coordinates in a 2D grid using [`i64`] coordinates, with wrapping
+//! arithmetic at the boundaries.
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::{Coordinate, Position};
+
+/// Represents a position in a 2D grid, bounded in each dimension by the maximum size of an [`i64`].
+/// Around the bounds, all arithmetic in this module with be done in a wrapping way, so be aware
+/// of that in your usage of this module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Component))]
+pub struct Position2 {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl From<(i64, i64)> for Position2 {
+    fn from(pair: (i64, i64)) -> Self {
+        Position2 {
+            x: pair.0,
+            y: pair.1,
+        }
+    }
+}
+
+impl Position2 {
+    /// Creates a new [`Position2`].
+    pub fn new(x: i64, y: i64) -> Self {
+        Position2 { x, y }
+    }
+
+    /// Returns whether or not we are adjacent to another position. Diagonal adjacency is not
+    /// counted:
+    /// ```
+    /// use positioning::Position2;
+    ///
+    /// assert!(!Position2::new(0, 0).is_adjacent_to(Position2::new(1, 1)))
+    /// ```
+    pub fn is_adjacent_to(self, other: Position2) -> bool {
+        self.hamming_distance(other) == 1
+    }
+
+    /// Returns an iterator over all adjacent positions.
+    pub fn adjacent(self) -> Box<dyn Iterator<Item = Position2>> {
+        Box::new(
+            [(-1, 0), (0, -1), (0, 1), (1, 0)]
+                .into_iter()
+                .map(move |(dx, dy)| self + Position2::new(dx, dy)),
+        )
+    }
+
+    /// Computes the Hamming distance between two points.
+    pub fn hamming_distance(self, other: Position2) -> u64 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// Computes the Chebyshev distance between two points, which is the largest difference along
+    /// either axis.
+    pub fn chebyshev_distance(self, other: Position2) -> u64 {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+
+    /// Lifts this position into 3D at the given z coordinate.
+    pub fn extend(self, z: i64) -> Position {
+        Position::new(self.x, self.y, z)
+    }
+}
+
+impl Coordinate for Position2 {
+    fn adjacent(self) -> Box<dyn Iterator<Item = Self>> {
+        Position2::adjacent(self)
+    }
+
+    fn distance(self, other: Self) -> u64 {
+        self.hamming_distance(other)
+    }
+
+    fn is_adjacent_to(self, other: Self) -> bool {
+        Position2::is_adjacent_to(self, other)
+    }
+}
+
+#[test]
+fn position2_adjacency_test() {
+    let a = Position2 { x: 1, y: 1 };
+    let b = Position2 { x: 2, ..a };
+    assert!(a.is_adjacent_to(b));
+    let c = Position2 { y: 2, ..a };
+    assert!(a.is_adjacent_to(c));
+    let d = Position2 { y: 2, ..b };
+    assert!(!a.is_adjacent_to(d));
+    let e = Position2 { y: 10, ..d };
+    assert!(!a.is_adjacent_to(e));
+
+    let adjacent: Vec<Position2> = a.adjacent().collect();
+    assert_eq!(adjacent.len(), 4);
+    assert!(adjacent.iter().all(|neighbor| a.is_adjacent_to(*neighbor)));
+    assert_eq!(a.extend(3).truncate(), a);
+}
+
+#[cfg(feature = "bevy")]
+impl From<bevy::prelude::Vec2> for Position2 {
+    fn from(v: bevy::prelude::Vec2) -> Self {
+        fn convert(f: f32) -> i64 {
+            if f < 0. {
+                -(-f as i64)
+            } else {
+                f as i64 + 1
+            }
+        }
+        Position2 {
+            x: convert(v.x),
+            y: convert(v.y),
+        }
+    }
+}
+
+impl Add<Position2> for Position2 {
+    type Output = Position2;
+
+    fn add(self, rhs: Position2) -> Self::Output {
+        Position2 {
+            x: self.x.wrapping_add(rhs.x),
+            y: self.y.wrapping_add(rhs.y),
+        }
+    }
+}
+
+impl Sub<Position2> for Position2 {
+    type Output = Position2;
+
+    fn sub(self, rhs: Position2) -> Self::Output {
+        Position2 {
+            x: self.x.wrapping_sub(rhs.x),
+            y: self.y.wrapping_sub(rhs.y),
+        }
+    }
+}
+
+impl Mul<i64> for Position2 {
+    type Output = Position2;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        Position2 {
+            x: self.x.wrapping_mul(rhs),
+            y: self.y.wrapping_mul(rhs),
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand::distributions::Distribution<Position2> for rand::distributions::Standard {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Position2 {
+        Position2::new(rng.gen(), rng.gen())
+    }
+}
@@ -0,0 +1,221 @@
+use std::{task::Poll, time::Instant};
+
+use crate::{
+    neighborhood::{Neighborhood, VonNeumann},
+    pathfinding::{AStar, Heuristic, PathError, PathResult, SearchStats, WithInfinity},
+    Coordinate, MovementCost, Node, Position,
+};
+
+/// # Resumable Search
+///
+/// A* search which can be run a slice at a time, so that a long search can be spread over several
+/// frames rather than stalling any one of them. Each call to [`ResumableSearch::run_for`] or
+/// [`ResumableSearch::run_until`] picks up where the last one left off, expanding positions until
+/// either the search finishes or the budget for that call runs out, and the search can be dropped
+/// at any point if the path is no longer needed.
+///
+/// When the budget runs out, [`ResumableSearch::partial_path`] gives a path to the position found
+/// so far which the heuristic says is closest to the end, for a unit to start moving along while
+/// the search continues.
+///
+/// Unlike the structures described under [changing maps](crate::pathfinding#changing-maps), a
+/// search cannot be told about changes, so if a tile changes in between calls it should be started
+/// again.
+pub struct ResumableSearch<H, C: Node = Position, N = VonNeumann> {
+    heuristic: H,
+    neighborhood: N,
+    start: C,
+    end: C,
+    search: AStar<C>,
+    closest: (WithInfinity<u64>, u64, C),
+    result: Option<Result<PathResult<C>, PathError>>,
+}
+
+impl<H: Heuristic<C>, C: Coordinate> ResumableSearch<H, C, VonNeumann> {
+    /// Starts a search for the cheapest path from `start` to `end`, guided by the given heuristic.
+    pub fn new(heuristic: H, start: C, end: C) -> Self {
+        ResumableSearch::with_neighborhood(heuristic, start, end, VonNeumann)
+    }
+}
+
+impl<H: Heuristic<C>, C: Node, N: Neighborhood<C>> ResumableSearch<H, C, N> {
+    /// Starts a search which moves between positions according to the given [`Neighborhood`].
+    pub fn with_neighborhood(heuristic: H, start: C, end: C, neighborhood: N) -> Self {
+        let estimate = heuristic.heuristic_distance(start, end);
+        let result =
+            (estimate == WithInfinity::Infinity).then_some(Err(PathError::HeuristicUnreachable));
+        ResumableSearch {
+            heuristic,
+            neighborhood,
+            start,
+            end,
+            search: AStar::new(start, estimate),
+            closest: (estimate, 0, start),
+            result,
+        }
+    }
+
+    /// The position the search is finding a path from.
+    pub fn start(&self) -> C {
+        self.start
+    }
+
+    /// The position the search is finding a path to.
+    pub fn end(&self) -> C {
+        self.end
+    }
+
+    /// The work done by the search so far.
+    pub fn stats(&self) -> SearchStats {
+        self.search.stats
+    }
+
+    /// Whether the search has either found a path or failed.
+    pub fn is_finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Runs the search for at most `expansions` more expansions, where the cost of each step is
+    /// given by the [`MovementCost`] map, as in [`Heuristic::try_find_cheapest_path`]. Returns
+    /// [`Poll::Pending`] if the search ran out of expansions before finishing, and otherwise the
+    /// result of the search, which is returned again by every later call.
+    pub fn run_for<M: MovementCost<C> + ?Sized>(
+        &mut self,
+        costs: &M,
+        expansions: usize,
+    ) -> Poll<Result<PathResult<C>, PathError>> {
+        self.run(costs, expansions, None)
+    }
+
+    /// Runs the search as in [`ResumableSearch::run_for`], until the given deadline has passed.
+    /// At least one position is expanded, so that repeated calls always make progress.
+    pub fn run_until<M: MovementCost<C> + ?Sized>(
+        &mut self,
+        costs: &M,
+        deadline: Instant,
+    ) -> Poll<Result<PathResult<C>, PathError>> {
+        self.run(costs, usize::MAX, Some(deadline))
+    }
+
+    /// The path to the position found so far which is closest to the end according to the
+    /// heuristic, preferring the cheaper to reach of positions which are equally close. Once a path
+    /// to the end has been found, this is that path.
+    pub fn partial_path(&self) -> PathResult<C> {
+        let (_, cost, closest) = self.closest;
+        PathResult {
+            path: self.search.path_to(closest),
+            cost,
+            stats: self.search.stats,
+        }
+    }
+
+    fn run<M: MovementCost<C> + ?Sized>(
+        &mut self,
+        costs: &M,
+        expansions: usize,
+        deadline: Option<Instant>,
+    ) -> Poll<Result<PathResult<C>, PathError>> {
+        for expanded in 0..expansions {
+            if let Some(result) = &self.result {
+                return Poll::Ready(result.clone());
+            }
+            if expanded > 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Poll::Pending;
+            }
+            self.expand(costs);
+        }
+        match &self.result {
+            Some(result) => Poll::Ready(result.clone()),
+            None => Poll::Pending,
+        }
+    }
+
+    fn expand<M: MovementCost<C> + ?Sized>(&mut self, costs: &M) {
+        let (position, distance) = match self.search.pop() {
+            None => {
+                self.result = Some(Err(PathError::Exhausted(self.search.stats)));
+                return;
+            }
+            Some(popped) => popped,
+        };
+        if position == self.end {
+            self.closest = (WithInfinity::Normal(0), distance, position);
+            self.result = Some(Ok(PathResult {
+                path: self.search.path_to(position),
+                cost: distance,
+                stats: self.search.stats,
+            }));
+            return;
+        }
+
+        let (heuristic, end, closest) = (&self.heuristic, self.end, &mut self.closest);
+        self.search
+            .expand(costs, &self.neighborhood, position, |neighbor, alt| {
+                let remaining = heuristic.heuristic_distance(neighbor, end);
+                if (remaining, alt) < (closest.0, closest.1) {
+                    *closest = (remaining, alt, neighbor);
+                }
+                remaining
+            });
+    }
+}
+
+#[test]
+fn resumable_search_test() {
+    use std::{collections::BTreeSet, time::Duration};
+
+    use crate::{pathfinding::HammingDistance, UnitCost};
+
+    // A room with a wall across it, open only at the far end.
+    let open_positions: BTreeSet<Position> = (0..20)
+        .flat_map(|x| (0..20).map(move |y| Position::new(x, y, 0)))
+        .filter(|position| position.x != 10 || position.y == 19)
+        .collect();
+    let costs = UnitCost(&open_positions);
+    let (start, end) = (Position::new(0, 0, 0), Position::new(19, 0, 0));
+    let expected = HammingDistance
+        .try_find_shortest_path(&open_positions, start, end)
+        .unwrap();
+
+    let mut search = ResumableSearch::new(HammingDistance, start, end);
+    assert_eq!(search.run_for(&costs, 0), Poll::Pending);
+    assert!(search.partial_path().path.is_empty());
+    let mut slices = 0;
+    let mut closest = start.distance(end);
+    let found = loop {
+        slices += 1;
+        if let Poll::Ready(result) = search.run_for(&costs, 10) {
+            break result.unwrap();
+        }
+        // The partial path only ever gets closer to the end.
+        let partial = search.partial_path();
+        assert_eq!(partial.cost, partial.path.len() as u64);
+        let reached = partial.path.back().copied().unwrap_or(start);
+        assert!(reached.distance(end) <= closest);
+        closest = reached.distance(end);
+    };
+    assert!(slices > 1);
+    assert!(search.is_finished());
+    assert_eq!(found.cost, expected.cost);
+    assert_eq!(found.stats, expected.stats);
+    assert_eq!(search.partial_path().path, found.path);
+    assert_eq!(search.run_for(&costs, 10), Poll::Ready(Ok(found)));
+
+    // A deadline which has already passed still expands a position on every call.
+    let mut search = ResumableSearch::new(HammingDistance, start, end);
+    assert_eq!(search.run_until(&costs, Instant::now()), Poll::Pending);
+    assert_eq!(search.stats().nodes_expanded, 1);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    assert!(matches!(
+        search.run_until(&costs, deadline),
+        Poll::Ready(Ok(_))
+    ));
+
+    let walled_in = Position::new(10, 0, 0);
+    let mut search = ResumableSearch::new(HammingDistance, start, walled_in);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    assert!(matches!(
+        search.run_until(&costs, deadline),
+        Poll::Ready(Err(PathError::Exhausted(_)))
+    ));
+}
@@ -0,0 +1,28 @@
+//! Assertions shared by the tests of the searches in this crate.
+
+use crate::{neighborhood::Neighborhood, Node, Passable};
+
+/// Asserts that `path` leads from `start` to `end` through open positions, each step moving
+/// between positions which are adjacent in the given [`Neighborhood`]. As with every search in
+/// this crate, the path excludes `start` and includes `end`.
+pub(crate) fn assert_valid_path<C: Node, P: Passable<C> + ?Sized, N: Neighborhood<C> + ?Sized>(
+    neighborhood: &N,
+    open_positions: &P,
+    start: C,
+    path: impl IntoIterator<Item = C>,
+    end: C,
+) {
+    let mut previous = start;
+    for position in path {
+        assert!(
+            neighborhood.are_adjacent(previous, position),
+            "{previous:?} is not adjacent to {position:?}"
+        );
+        assert!(
+            open_positions.is_passable(position),
+            "{position:?} is not open"
+        );
+        previous = position;
+    }
+    assert_eq!(previous, end);
+}